@@ -0,0 +1,316 @@
+use crate::{
+    matrix::{Matrix, EPSILON},
+    rays::Ray,
+    tuple::{point, Tuple},
+};
+
+// An axis-aligned bounding box.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub min: Tuple,
+    pub max: Tuple,
+}
+
+impl BoundingBox {
+    pub fn new(min: Tuple, max: Tuple) -> BoundingBox {
+        BoundingBox { min, max }
+    }
+
+    // A box containing nothing; adding anything to it gives a box around just that.
+    pub fn empty() -> BoundingBox {
+        BoundingBox {
+            min: point(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            max: point(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+        }
+    }
+
+    // A box containing everything.
+    pub fn infinite() -> BoundingBox {
+        BoundingBox {
+            min: point(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+            max: point(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+        }
+    }
+
+    pub fn is_finite(&self) -> bool {
+        [
+            self.min.x, self.min.y, self.min.z, self.max.x, self.max.y, self.max.z,
+        ]
+        .iter()
+        .all(|v| v.is_finite())
+    }
+
+    // True for a box with nothing in it, like the bounds of an empty group.
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn add_point(&mut self, p: &Tuple) {
+        self.min = point(
+            self.min.x.min(p.x),
+            self.min.y.min(p.y),
+            self.min.z.min(p.z),
+        );
+        self.max = point(
+            self.max.x.max(p.x),
+            self.max.y.max(p.y),
+            self.max.z.max(p.z),
+        );
+    }
+
+    pub fn merge(&mut self, other: &BoundingBox) {
+        // An empty box's corners are infinities, which would make this box infinite.
+        if other.is_empty() {
+            return;
+        }
+        self.add_point(&other.min);
+        self.add_point(&other.max);
+    }
+
+    pub fn contains_point(&self, p: &Tuple) -> bool {
+        (self.min.x..=self.max.x).contains(&p.x)
+            && (self.min.y..=self.max.y).contains(&p.y)
+            && (self.min.z..=self.max.z).contains(&p.z)
+    }
+
+    pub fn contains_box(&self, other: &BoundingBox) -> bool {
+        self.contains_point(&other.min) && self.contains_point(&other.max)
+    }
+
+    pub fn centroid(&self) -> Tuple {
+        point(
+            (self.min.x + self.max.x) / 2.0,
+            (self.min.y + self.max.y) / 2.0,
+            (self.min.z + self.max.z) / 2.0,
+        )
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        let dx = (self.max.x - self.min.x).max(0.0);
+        let dy = (self.max.y - self.min.y).max(0.0);
+        let dz = (self.max.z - self.min.z).max(0.0);
+        2.0 * (dx * dy + dy * dz + dz * dx)
+    }
+
+    // Returns a new axis-aligned box that fits around this box after it has been transformed.
+    pub fn transform(&self, m: &Matrix) -> BoundingBox {
+        if self.is_empty() {
+            return BoundingBox::empty();
+        }
+        if !self.is_finite() {
+            // Infinite extents can't be rotated meaningfully, so be conservative.
+            return BoundingBox::infinite();
+        }
+
+        let (min, max) = (&self.min, &self.max);
+        let corners = [
+            point(min.x, min.y, min.z),
+            point(min.x, min.y, max.z),
+            point(min.x, max.y, min.z),
+            point(min.x, max.y, max.z),
+            point(max.x, min.y, min.z),
+            point(max.x, min.y, max.z),
+            point(max.x, max.y, min.z),
+            point(max.x, max.y, max.z),
+        ];
+
+        let mut result = BoundingBox::empty();
+        for corner in corners {
+            result.add_point(&(m * corner));
+        }
+        result
+    }
+
+    // Slab test, the same approach the cube uses but with arbitrary extents.
+    pub fn intersects(&self, ray: &Ray) -> bool {
+        let (xtmin, xtmax) = check_axis(ray.origin.x, ray.direction.x, self.min.x, self.max.x);
+        let (ytmin, ytmax) = check_axis(ray.origin.y, ray.direction.y, self.min.y, self.max.y);
+        let (ztmin, ztmax) = check_axis(ray.origin.z, ray.direction.z, self.min.z, self.max.z);
+
+        let tmin = xtmin.max(ytmin).max(ztmin);
+        let tmax = xtmax.min(ytmax).min(ztmax);
+
+        tmin <= tmax
+    }
+}
+
+fn check_axis(origin: f64, direction: f64, min: f64, max: f64) -> (f64, f64) {
+    if direction.abs() < EPSILON {
+        // Parallel to the slab, so either always inside it or never.
+        return if (min..=max).contains(&origin) {
+            (f64::NEG_INFINITY, f64::INFINITY)
+        } else {
+            (f64::INFINITY, f64::NEG_INFINITY)
+        };
+    }
+
+    let tmin = (min - origin) / direction;
+    let tmax = (max - origin) / direction;
+
+    if tmin > tmax {
+        (tmax, tmin)
+    } else {
+        (tmin, tmax)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use crate::{
+        rays::ray,
+        transformations::{rotation_x, rotation_y, scaling},
+        tuple::{normalise, vector},
+    };
+
+    use super::*;
+
+    #[test]
+    fn adding_points_to_an_empty_bounding_box() {
+        let mut b = BoundingBox::empty();
+        b.add_point(&point(-5.0, 2.0, 0.0));
+        b.add_point(&point(7.0, 0.0, -3.0));
+        assert_eq!(b.min, point(-5.0, 0.0, -3.0));
+        assert_eq!(b.max, point(7.0, 2.0, 0.0));
+    }
+
+    #[test]
+    fn merging_two_bounding_boxes() {
+        let mut b1 = BoundingBox::new(point(-5.0, -2.0, 0.0), point(7.0, 4.0, 4.0));
+        let b2 = BoundingBox::new(point(8.0, -7.0, -2.0), point(14.0, 2.0, 8.0));
+        b1.merge(&b2);
+        assert_eq!(b1.min, point(-5.0, -7.0, -2.0));
+        assert_eq!(b1.max, point(14.0, 4.0, 8.0));
+    }
+
+    #[test]
+    fn merging_with_an_empty_box_changes_nothing() {
+        let b = BoundingBox::new(point(-1.0, -2.0, -3.0), point(1.0, 2.0, 3.0));
+        let mut merged = b.clone();
+        merged.merge(&BoundingBox::empty());
+        assert_eq!(merged, b);
+
+        let mut empty = BoundingBox::empty();
+        empty.merge(&b);
+        assert_eq!(empty, b);
+
+        let mut both = BoundingBox::empty();
+        both.merge(&BoundingBox::empty());
+        assert!(both.is_empty());
+    }
+
+    #[test]
+    fn transforming_an_empty_box_stays_empty() {
+        let b = BoundingBox::empty().transform(&scaling(2.0, 2.0, 2.0));
+        assert!(b.is_empty());
+    }
+
+    #[test]
+    fn checking_whether_box_contains_a_point() {
+        let b = BoundingBox::new(point(5.0, -2.0, 0.0), point(11.0, 4.0, 7.0));
+        let examples = [
+            (point(5.0, -2.0, 0.0), true),
+            (point(11.0, 4.0, 7.0), true),
+            (point(8.0, 1.0, 3.0), true),
+            (point(3.0, 0.0, 3.0), false),
+            (point(8.0, -4.0, 3.0), false),
+            (point(8.0, 1.0, -1.0), false),
+            (point(13.0, 1.0, 3.0), false),
+            (point(8.0, 5.0, 3.0), false),
+            (point(8.0, 1.0, 8.0), false),
+        ];
+        for (p, expected) in examples {
+            assert_eq!(b.contains_point(&p), expected);
+        }
+    }
+
+    #[test]
+    fn checking_whether_box_contains_a_box() {
+        let b = BoundingBox::new(point(5.0, -2.0, 0.0), point(11.0, 4.0, 7.0));
+        let examples = [
+            (point(5.0, -2.0, 0.0), point(11.0, 4.0, 7.0), true),
+            (point(6.0, -1.0, 1.0), point(10.0, 3.0, 6.0), true),
+            (point(4.0, -3.0, -1.0), point(10.0, 3.0, 6.0), false),
+            (point(6.0, -1.0, 1.0), point(12.0, 5.0, 8.0), false),
+        ];
+        for (min, max, expected) in examples {
+            assert_eq!(b.contains_box(&BoundingBox::new(min, max)), expected);
+        }
+    }
+
+    #[test]
+    fn transforming_a_bounding_box() {
+        let b = BoundingBox::new(point(-1.0, -1.0, -1.0), point(1.0, 1.0, 1.0));
+        let m = rotation_x(PI / 4.0) * rotation_y(PI / 4.0);
+        let b2 = b.transform(&m);
+        let (x, yz) = (2.0_f64.sqrt(), 1.0 + 2.0_f64.sqrt() / 2.0);
+        assert_eq!(b2.min, point(-x, -yz, -yz));
+        assert_eq!(b2.max, point(x, yz, yz));
+    }
+
+    #[test]
+    fn transforming_an_infinite_box_stays_infinite() {
+        let b = BoundingBox::new(
+            point(f64::NEG_INFINITY, 0.0, f64::NEG_INFINITY),
+            point(f64::INFINITY, 0.0, f64::INFINITY),
+        );
+        let b = b.transform(&rotation_x(PI / 4.0));
+        assert_eq!(b, BoundingBox::infinite());
+    }
+
+    #[test]
+    fn intersecting_ray_with_bounding_box_at_origin() {
+        let b = BoundingBox::new(point(-1.0, -1.0, -1.0), point(1.0, 1.0, 1.0));
+        let examples = [
+            (point(5.0, 0.5, 0.0), vector(-1.0, 0.0, 0.0), true),
+            (point(-5.0, 0.5, 0.0), vector(1.0, 0.0, 0.0), true),
+            (point(0.5, 5.0, 0.0), vector(0.0, -1.0, 0.0), true),
+            (point(0.5, -5.0, 0.0), vector(0.0, 1.0, 0.0), true),
+            (point(0.5, 0.0, 5.0), vector(0.0, 0.0, -1.0), true),
+            (point(0.5, 0.0, -5.0), vector(0.0, 0.0, 1.0), true),
+            (point(0.0, 0.5, 0.0), vector(0.0, 0.0, 1.0), true),
+            (point(-2.0, 0.0, 0.0), vector(2.0, 4.0, 6.0), false),
+            (point(0.0, -2.0, 0.0), vector(6.0, 2.0, 4.0), false),
+            (point(0.0, 0.0, -2.0), vector(4.0, 6.0, 2.0), false),
+            (point(2.0, 0.0, 2.0), vector(0.0, 0.0, -1.0), false),
+            (point(0.0, 2.0, 2.0), vector(0.0, -1.0, 0.0), false),
+            (point(2.0, 2.0, 0.0), vector(-1.0, 0.0, 0.0), false),
+        ];
+        for (origin, direction, expected) in examples {
+            let r = ray(origin, normalise(&direction));
+            assert_eq!(b.intersects(&r), expected);
+        }
+    }
+
+    #[test]
+    fn intersecting_ray_with_non_cubic_bounding_box() {
+        let b = BoundingBox::new(point(5.0, -2.0, 0.0), point(11.0, 4.0, 7.0));
+        let examples = [
+            (point(15.0, 1.0, 2.0), vector(-1.0, 0.0, 0.0), true),
+            (point(-5.0, -1.0, 4.0), vector(1.0, 0.0, 0.0), true),
+            (point(7.0, 6.0, 5.0), vector(0.0, -1.0, 0.0), true),
+            (point(9.0, -5.0, 6.0), vector(0.0, 1.0, 0.0), true),
+            (point(8.0, 2.0, 12.0), vector(0.0, 0.0, -1.0), true),
+            (point(6.0, 0.0, -5.0), vector(0.0, 0.0, 1.0), true),
+            (point(8.0, 1.0, 3.5), vector(0.0, 0.0, 1.0), true),
+            (point(9.0, -1.0, -8.0), vector(2.0, 4.0, 6.0), false),
+            (point(8.0, 3.0, -4.0), vector(6.0, 2.0, 4.0), false),
+            (point(9.0, -1.0, -2.0), vector(4.0, 6.0, 2.0), false),
+            (point(4.0, 0.0, 9.0), vector(0.0, 0.0, -1.0), false),
+            (point(8.0, 6.0, -1.0), vector(0.0, -1.0, 0.0), false),
+            (point(12.0, 5.0, 4.0), vector(-1.0, 0.0, 0.0), false),
+        ];
+        for (origin, direction, expected) in examples {
+            let r = ray(origin, normalise(&direction));
+            assert_eq!(b.intersects(&r), expected);
+        }
+    }
+
+    #[test]
+    fn ray_always_intersects_infinite_box() {
+        let b = BoundingBox::infinite();
+        let r = ray(point(0.0, 100.0, 0.0), vector(0.0, 0.0, 1.0));
+        assert!(b.intersects(&r));
+    }
+}
@@ -0,0 +1,317 @@
+use crate::{bounds::BoundingBox, intersections::Intersection, rays::Ray, shape::Shape};
+
+// Nodes with this many shapes or fewer are never split.
+const MAX_LEAF_SIZE: usize = 2;
+
+// Cost of testing a ray against a box relative to testing it against a shape.
+const TRAVERSAL_COST: f64 = 0.125;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Leaf {
+        bounds: BoundingBox,
+        shapes: Vec<usize>,
+    },
+    Branch {
+        bounds: BoundingBox,
+        left: Box<Node>,
+        right: Box<Node>,
+    },
+}
+
+impl Node {
+    fn bounds(&self) -> &BoundingBox {
+        match self {
+            Node::Leaf { bounds, .. } => bounds,
+            Node::Branch { bounds, .. } => bounds,
+        }
+    }
+}
+
+// A bounding volume hierarchy over a list of shapes.
+// It only stores indices, so it must be used with the same list it was built from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bvh {
+    root: Option<Node>,
+    // Shapes like planes go on forever, so there is no point putting them in a box.
+    unbounded: Vec<usize>,
+    len: usize,
+}
+
+impl Bvh {
+    pub fn build(shapes: &[Shape]) -> Bvh {
+        let mut bounded = vec![];
+        let mut unbounded = vec![];
+        for (index, shape) in shapes.iter().enumerate() {
+            let bounds = shape.parent_space_bounds();
+            if bounds.is_empty() {
+                // Nothing there to hit, like an empty group.
+                continue;
+            }
+            if bounds.is_finite() {
+                bounded.push((index, bounds));
+            } else {
+                unbounded.push(index);
+            }
+        }
+
+        let root = if bounded.is_empty() {
+            None
+        } else {
+            Some(build_node(bounded))
+        };
+
+        Bvh {
+            root,
+            unbounded,
+            len: shapes.len(),
+        }
+    }
+
+    // The number of shapes the hierarchy was built over.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // The indices of the shapes whose bounds the ray passes through, and so might be hit.
+    pub fn candidates(&self, ray: &Ray) -> Vec<usize> {
+        let mut result = self.unbounded.clone();
+        let mut stack = vec![];
+        if let Some(root) = &self.root {
+            stack.push(root);
+        }
+
+        while let Some(node) = stack.pop() {
+            if !node.bounds().intersects(ray) {
+                continue;
+            }
+            match node {
+                Node::Leaf { shapes, .. } => result.extend(shapes),
+                Node::Branch { left, right, .. } => {
+                    stack.push(left);
+                    stack.push(right);
+                }
+            }
+        }
+
+        result
+    }
+}
+
+fn build_node(mut items: Vec<(usize, BoundingBox)>) -> Node {
+    let mut bounds = BoundingBox::empty();
+    for (_, b) in &items {
+        bounds.merge(b);
+    }
+
+    let leaf_cost = items.len() as f64;
+    let split = if items.len() > MAX_LEAF_SIZE {
+        best_split(&mut items, &bounds)
+    } else {
+        None
+    };
+
+    match split {
+        Some((axis, index, cost)) if cost < leaf_cost => {
+            sort_by_centroid(&mut items, axis);
+            let right = items.split_off(index);
+            Node::Branch {
+                bounds,
+                left: Box::new(build_node(items)),
+                right: Box::new(build_node(right)),
+            }
+        }
+        _ => Node::Leaf {
+            bounds,
+            shapes: items.into_iter().map(|(index, _)| index).collect(),
+        },
+    }
+}
+
+// Surface area heuristic: the chance of a ray hitting a child box is proportional to its
+// surface area, so pick the split that minimises area times number of shapes on each side.
+// Returns the axis, the position to split the sorted list at, and the estimated cost.
+fn best_split(
+    items: &mut [(usize, BoundingBox)],
+    bounds: &BoundingBox,
+) -> Option<(usize, usize, f64)> {
+    let area = bounds.surface_area();
+    let mut best: Option<(usize, usize, f64)> = None;
+
+    for axis in 0..3 {
+        sort_by_centroid(items, axis);
+
+        // Areas of everything to the right of each split position.
+        let mut right_areas = vec![0.0; items.len()];
+        let mut right_bounds = BoundingBox::empty();
+        for i in (1..items.len()).rev() {
+            right_bounds.merge(&items[i].1);
+            right_areas[i] = right_bounds.surface_area();
+        }
+
+        let mut left_bounds = BoundingBox::empty();
+        for i in 1..items.len() {
+            left_bounds.merge(&items[i - 1].1);
+            let left_count = i as f64;
+            let right_count = (items.len() - i) as f64;
+            let cost = if area > 0.0 {
+                TRAVERSAL_COST
+                    + (left_bounds.surface_area() * left_count + right_areas[i] * right_count)
+                        / area
+            } else {
+                // Everything is squashed into a point or a line, so just halve the list.
+                TRAVERSAL_COST + left_count.max(right_count)
+            };
+
+            if best.is_none_or(|(_, _, c)| cost < c) {
+                best = Some((axis, i, cost));
+            }
+        }
+    }
+
+    best
+}
+
+fn sort_by_centroid(items: &mut [(usize, BoundingBox)], axis: usize) {
+    items.sort_by(|(_, a), (_, b)| {
+        let (a, b) = (a.centroid(), b.centroid());
+        match axis {
+            0 => a.x.total_cmp(&b.x),
+            1 => a.y.total_cmp(&b.y),
+            _ => a.z.total_cmp(&b.z),
+        }
+    });
+}
+
+// Intersects the ray with only those shapes it could possibly hit. Not sorted.
+pub fn intersect(bvh: &Bvh, shapes: &[Shape], ray: &Ray) -> Vec<Intersection> {
+    let mut result = vec![];
+    for index in bvh.candidates(ray) {
+        result.extend(shapes[index].intersect(ray));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        plane::plane,
+        rays::ray,
+        sphere::sphere,
+        transformations::{scaling, translation},
+        tuple::{point, vector},
+    };
+
+    use super::*;
+
+    // A cube of spheres, n along each side.
+    fn grid_of_spheres(n: usize) -> Vec<Shape> {
+        let mut result = vec![];
+        for x in 0..n {
+            for y in 0..n {
+                for z in 0..n {
+                    let mut s = sphere();
+                    s.set_transform(
+                        translation(x as f64 * 3.0, y as f64 * 3.0, z as f64 * 3.0)
+                            * scaling(0.5, 0.5, 0.5),
+                    );
+                    result.push(s);
+                }
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn empty_bvh_has_no_candidates() {
+        let bvh = Bvh::build(&[]);
+        let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        assert!(bvh.is_empty());
+        assert!(bvh.candidates(&r).is_empty());
+    }
+
+    #[test]
+    fn unbounded_shapes_are_always_candidates() {
+        let shapes = vec![plane(), sphere()];
+        let bvh = Bvh::build(&shapes);
+        let r = ray(point(10.0, 10.0, -5.0), vector(0.0, 0.0, 1.0));
+        assert_eq!(bvh.candidates(&r), vec![0]);
+    }
+
+    #[test]
+    fn ray_only_tests_shapes_whose_bounds_it_passes_through() {
+        let shapes = grid_of_spheres(4);
+        let bvh = Bvh::build(&shapes);
+        assert_eq!(bvh.len(), 64);
+
+        let r = ray(point(3.0, 6.0, -5.0), vector(0.0, 0.0, 1.0));
+        let candidates = bvh.candidates(&r);
+        // The row of spheres along the ray, plus at most whatever shares a leaf with them.
+        for z in 0..4 {
+            assert!(candidates.contains(&(16 + 2 * 4 + z)));
+        }
+        assert!(candidates.len() <= 4 * MAX_LEAF_SIZE);
+    }
+
+    #[test]
+    fn bvh_finds_the_same_intersections_as_brute_force() {
+        let shapes = grid_of_spheres(5);
+        let bvh = Bvh::build(&shapes);
+        for (origin, direction) in [
+            (point(-5.0, 6.0, 6.0), vector(1.0, 0.0, 0.0)),
+            (point(-5.0, -5.0, -5.0), vector(1.0, 1.0, 1.0)),
+            (point(6.2, 30.0, 5.8), vector(0.0, -1.0, 0.0)),
+            (point(1.5, 1.5, -5.0), vector(0.0, 0.0, 1.0)),
+        ] {
+            let r = ray(origin, direction);
+            let mut expected: Vec<Intersection> =
+                shapes.iter().flat_map(|s| s.intersect(&r)).collect();
+            let mut actual = intersect(&bvh, &shapes, &r);
+            expected.sort_by(|a, b| a.t.total_cmp(&b.t));
+            actual.sort_by(|a, b| a.t.total_cmp(&b.t));
+            assert_eq!(actual, expected);
+        }
+    }
+
+    // Not a timing benchmark, but counts how many ray/shape tests each approach needs,
+    // which is what dominates the render time for big meshes.
+    #[test]
+    fn benchmark_brute_force_against_bvh_shape_tests() {
+        let n = 5;
+        let shapes = grid_of_spheres(n);
+        let bvh = Bvh::build(&shapes);
+
+        let mut brute_force_tests = 0;
+        let mut bvh_tests = 0;
+        let mut brute_force_hits = 0;
+        let mut bvh_hits = 0;
+        for i in 0..n * 3 {
+            for j in 0..n * 3 {
+                let r = ray(point(i as f64, j as f64, -5.0), vector(0.0, 0.0, 1.0));
+
+                brute_force_tests += shapes.len();
+                brute_force_hits += shapes
+                    .iter()
+                    .filter(|s| !s.intersect(&r).is_empty())
+                    .count();
+
+                let candidates = bvh.candidates(&r);
+                bvh_tests += candidates.len();
+                bvh_hits += candidates
+                    .iter()
+                    .filter(|&&c| !shapes[c].intersect(&r).is_empty())
+                    .count();
+            }
+        }
+
+        assert_eq!(brute_force_tests, 15 * 15 * 125);
+        assert!(brute_force_hits > 0);
+        assert_eq!(bvh_hits, brute_force_hits);
+        // Each ray only gets near one column of spheres.
+        assert!(bvh_tests * 50 < brute_force_tests);
+    }
+}
@@ -1,11 +1,29 @@
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
 use crate::{
     canvas::Canvas,
+    color::Color,
     matrix::{inverse, Matrix},
+    noise::hash_to_unit,
     rays::{ray, Ray},
     tuple::{normalise, point},
-    world::{color_at, World},
+    world::{color_at, World, MAX_REFLECTION_DEPTH},
 };
 
+// Where in each pixel the rays for anti-aliasing go.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Sampling {
+    // The centres of an even grid of cells. No noise, but regular patterns can still alias.
+    Grid,
+    // A random point in each grid cell, which swaps aliasing for a little noise.
+    Jittered,
+    // Random points anywhere in the pixel; noisier, since samples can bunch up.
+    Random,
+}
+
 pub struct Camera {
     pub hsize: u64,
     pub vsize: u64,
@@ -14,6 +32,14 @@ pub struct Camera {
     pub half_width: f64,
     pub half_height: f64,
     pub pixel_size: f64,
+    // How many threads render uses, 1 renders on the calling thread.
+    pub threads: usize,
+    // Rays per pixel, averaged together. Grid and jittered sampling use the squarest grid
+    // with exactly that many cells, e.g. 2x4 for 8 samples, or a single row of 7 for 7.
+    pub samples: usize,
+    pub sampling: Sampling,
+    // Changing the seed gives different random samples; the same seed gives the same image.
+    pub seed: u64,
 }
 
 impl Camera {
@@ -28,6 +54,10 @@ impl Camera {
             half_width,
             half_height,
             pixel_size,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            samples: 1,
+            sampling: Sampling::Grid,
+            seed: 0,
         }
     }
 
@@ -46,35 +76,141 @@ impl Camera {
 
 // TODO: move onto Camera
 pub fn ray_for_pixel(camera: &Camera, px: u64, py: u64) -> Ray {
-    let xoffset = (px as f64 + 0.5) * camera.pixel_size;
-    let yoffset = (py as f64 + 0.5) * camera.pixel_size;
+    ray_through_pixel(camera, &inverse(&camera.transform), px, py, 0.5, 0.5)
+}
+
+// A ray through the point (dx, dy) of the way across and down the pixel. Takes the inverse
+// of the camera's transform so a render only has to invert it once.
+fn ray_through_pixel(
+    camera: &Camera,
+    inverse_transform: &Matrix,
+    px: u64,
+    py: u64,
+    dx: f64,
+    dy: f64,
+) -> Ray {
+    let xoffset = (px as f64 + dx) * camera.pixel_size;
+    let yoffset = (py as f64 + dy) * camera.pixel_size;
     let world_x = camera.half_width - xoffset;
     let world_y = camera.half_height - yoffset;
-    let pixel = inverse(&camera.transform) * point(world_x, world_y, -1.0);
-    let origin = inverse(&camera.transform) * point(0.0, 0.0, 0.0);
+    let pixel = inverse_transform * point(world_x, world_y, -1.0);
+    let origin = inverse_transform * point(0.0, 0.0, 0.0);
     let direction = normalise(&(&pixel - &origin));
     ray(origin, direction)
 }
 
+// The points within pixel (px, py) to send rays through, as fractions of the pixel size.
+fn sample_offsets(camera: &Camera, px: u64, py: u64) -> Vec<(f64, f64)> {
+    let random = |sample: usize, axis: u64| random_offset(camera.seed, px, py, sample, axis);
+    let samples = camera.samples.max(1);
+
+    if camera.sampling == Sampling::Random {
+        return (0..samples).map(|i| (random(i, 0), random(i, 1))).collect();
+    }
+
+    // The largest factor no bigger than the square root gives the squarest grid.
+    let rows = (1..=(samples as f64).sqrt() as usize)
+        .rev()
+        .find(|&rows| samples.is_multiple_of(rows))
+        .unwrap_or(1);
+    let columns = samples / rows;
+    let mut result = Vec::with_capacity(samples);
+    for row in 0..rows {
+        for column in 0..columns {
+            let (jx, jy) = match camera.sampling {
+                Sampling::Jittered => {
+                    let i = row * columns + column;
+                    (random(i, 0), random(i, 1))
+                }
+                _ => (0.5, 0.5),
+            };
+            result.push((
+                (column as f64 + jx) / columns as f64,
+                (row as f64 + jy) / rows as f64,
+            ));
+        }
+    }
+    result
+}
+
+// A pseudo-random number in [0, 1) for one sample of one pixel.
+fn random_offset(seed: u64, px: u64, py: u64, sample: usize, axis: u64) -> f64 {
+    hash_to_unit(&[seed, px, py, sample as u64, axis])
+}
+
 pub fn render(camera: &Camera, world: &World) -> Canvas {
     let mut image = Canvas::new(camera.hsize as usize, camera.vsize as usize);
+    let inverse_transform = inverse(&camera.transform);
 
-    for y in 0..camera.vsize {
-        for x in 0..camera.hsize {
-            let ray = ray_for_pixel(camera, x, y);
-            let color = color_at(world, &ray);
-            image.write_pixel(x as usize, y as usize, color);
+    if camera.threads <= 1 {
+        for y in 0..camera.vsize {
+            for (x, color) in render_line(camera, &inverse_transform, world, y)
+                .into_iter()
+                .enumerate()
+            {
+                image.write_pixel(x, y as usize, color);
+            }
+        }
+        return image;
+    }
+
+    // Each thread keeps taking the next unrendered line until there are none left, so
+    // threads that get cheap lines (e.g. mostly background) don't sit idle.
+    let next_line = AtomicUsize::new(0);
+    let rendered = thread::scope(|scope| {
+        let workers: Vec<_> = (0..camera.threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut lines = vec![];
+                    loop {
+                        let y = next_line.fetch_add(1, Ordering::Relaxed) as u64;
+                        if y >= camera.vsize {
+                            return lines;
+                        }
+                        lines.push((y, render_line(camera, &inverse_transform, world, y)));
+                    }
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .flat_map(|w| w.join().expect("render thread panicked"))
+            .collect::<Vec<_>>()
+    });
+
+    for (y, line) in rendered {
+        for (x, color) in line.into_iter().enumerate() {
+            image.write_pixel(x, y as usize, color);
         }
     }
     image
 }
 
+// Every pixel is calculated independently, so lines come out the same whichever
+// thread renders them.
+fn render_line(camera: &Camera, inverse_transform: &Matrix, world: &World, y: u64) -> Vec<Color> {
+    (0..camera.hsize)
+        .map(|x| {
+            let offsets = sample_offsets(camera, x, y);
+            let total = offsets
+                .iter()
+                .map(|&(dx, dy)| {
+                    let ray = ray_through_pixel(camera, inverse_transform, x, y, dx, dy);
+                    color_at(world, &ray, MAX_REFLECTION_DEPTH)
+                })
+                .fold(Color::black(), |total, color| total + color);
+            total * (1.0 / offsets.len() as f64)
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use std::f64::consts::PI;
 
     use crate::{
-        color::Color,
+        matrix::EPSILON,
         transformations::{rotation_y, translation, view_transform},
         tuple::{point, vector},
         world::World,
@@ -150,4 +286,168 @@ mod tests {
         let image = render(&c, &w);
         assert_eq!(image.pixel_at(5, 5), &Color::new(0.38066, 0.47583, 0.2855));
     }
+
+    #[test]
+    fn rendering_with_threads_matches_rendering_serially() {
+        let mut w = World::default();
+        w.build_bvh();
+        let mut c = Camera::new(33, 21, PI / 2.0);
+        c.transform = view_transform(
+            &point(0.0, 0.5, -3.0),
+            &point(0.0, 0.0, 0.0),
+            &vector(0.0, 1.0, 0.0),
+        );
+        c.threads = 1;
+        let serial = render(&c, &w);
+
+        for threads in [2, 3, 8, 64] {
+            c.threads = threads;
+            let parallel = render(&c, &w);
+            for y in 0..21 {
+                for x in 0..33 {
+                    let (a, b) = (serial.pixel_at(x, y), parallel.pixel_at(x, y));
+                    // Exactly the same, not just close.
+                    assert_eq!(a.red.to_bits(), b.red.to_bits());
+                    assert_eq!(a.green.to_bits(), b.green.to_bits());
+                    assert_eq!(a.blue.to_bits(), b.blue.to_bits());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn world_can_be_shared_between_threads() {
+        fn assert_sync<T: Sync>() {}
+        assert_sync::<World>();
+        assert_sync::<crate::shape::Shape>();
+        assert_sync::<crate::materials::Material>();
+    }
+
+    #[test]
+    fn one_grid_sample_is_the_pixel_centre() {
+        let c = Camera::new(201, 101, PI / 2.0);
+        assert_eq!(sample_offsets(&c, 10, 20), vec![(0.5, 0.5)]);
+    }
+
+    #[test]
+    fn grid_samples_are_cell_centres() {
+        let mut c = Camera::new(201, 101, PI / 2.0);
+        c.samples = 4;
+        assert_eq!(
+            sample_offsets(&c, 0, 0),
+            vec![(0.25, 0.25), (0.75, 0.25), (0.25, 0.75), (0.75, 0.75)]
+        );
+    }
+
+    #[test]
+    fn grid_samples_fill_a_grid_when_the_count_is_not_square() {
+        let mut c = Camera::new(201, 101, PI / 2.0);
+        c.samples = 6;
+        // 2 rows of 3.
+        let offsets = sample_offsets(&c, 5, 9);
+        assert_eq!(offsets.len(), 6);
+        for (i, &(dx, dy)) in offsets.iter().enumerate() {
+            let (column, row) = ((i % 3) as f64, (i / 3) as f64);
+            assert!((dx - (column + 0.5) / 3.0).abs() < EPSILON);
+            assert!((dy - (row + 0.5) / 2.0).abs() < EPSILON);
+        }
+        assert_eq!(offsets, sample_offsets(&c, 6, 10));
+
+        // A prime count can only be a single row.
+        c.samples = 7;
+        let offsets = sample_offsets(&c, 0, 0);
+        assert_eq!(offsets.len(), 7);
+        for (i, &(dx, dy)) in offsets.iter().enumerate() {
+            assert!((dx - (i as f64 + 0.5) / 7.0).abs() < EPSILON);
+            assert_eq!(dy, 0.5);
+        }
+    }
+
+    #[test]
+    fn jittered_samples_stay_in_their_cells() {
+        let mut c = Camera::new(201, 101, PI / 2.0);
+        c.samples = 9;
+        c.sampling = Sampling::Jittered;
+        let offsets = sample_offsets(&c, 3, 4);
+        assert_eq!(offsets.len(), 9);
+        for (i, &(dx, dy)) in offsets.iter().enumerate() {
+            let (column, row) = ((i % 3) as f64, (i / 3) as f64);
+            assert!(dx >= column / 3.0 && dx < (column + 1.0) / 3.0);
+            assert!(dy >= row / 3.0 && dy < (row + 1.0) / 3.0);
+        }
+        assert_ne!(offsets, sample_offsets(&c, 4, 3));
+    }
+
+    #[test]
+    fn random_samples_depend_only_on_the_seed() {
+        let mut c = Camera::new(201, 101, PI / 2.0);
+        c.samples = 5;
+        c.sampling = Sampling::Random;
+        let offsets = sample_offsets(&c, 7, 7);
+        assert_eq!(offsets.len(), 5);
+        assert!(offsets
+            .iter()
+            .all(|&(dx, dy)| (0.0..1.0).contains(&dx) && (0.0..1.0).contains(&dy)));
+        assert_eq!(offsets, sample_offsets(&c, 7, 7));
+        c.seed = 1;
+        assert_ne!(offsets, sample_offsets(&c, 7, 7));
+    }
+
+    #[test]
+    fn supersampling_blends_the_edges_of_shapes() {
+        // A sphere that is exactly white wherever it is hit, against black.
+        let mut w = World::default();
+        w.objects_mut().truncate(1);
+        w.objects_mut()[0].set_material(
+            crate::materials::Material::default()
+                .ambient(1.0)
+                .diffuse(0.0)
+                .specular(0.0),
+        );
+        let mut c = Camera::new(15, 15, PI / 3.0);
+        c.transform = view_transform(
+            &point(0.0, 0.0, -5.0),
+            &point(0.0, 0.0, 0.0),
+            &vector(0.0, 1.0, 0.0),
+        );
+
+        let levels = |image: &Canvas, samples: f64| {
+            let mut blended = 0;
+            for y in 0..15 {
+                for x in 0..15 {
+                    let v = image.pixel_at(x, y).red * samples;
+                    // Every pixel is the fraction of its samples that hit.
+                    assert!((v - v.round()).abs() < 1e-9);
+                    if v.round() > 0.0 && v.round() < samples {
+                        blended += 1;
+                    }
+                }
+            }
+            blended
+        };
+
+        assert_eq!(levels(&render(&c, &w), 1.0), 0);
+        c.samples = 16;
+        assert!(levels(&render(&c, &w), 16.0) > 0);
+    }
+
+    #[test]
+    fn supersampled_renders_are_reproducible() {
+        let w = World::default();
+        let mut c = Camera::new(9, 7, PI / 2.0);
+        c.transform = view_transform(
+            &point(0.0, 0.5, -3.0),
+            &point(0.0, 0.0, 0.0),
+            &vector(0.0, 1.0, 0.0),
+        );
+        c.samples = 4;
+        c.sampling = Sampling::Jittered;
+        c.seed = 42;
+        c.threads = 1;
+        let serial = render(&c, &w);
+        c.threads = 4;
+        assert_eq!(render(&c, &w), serial);
+        c.seed = 43;
+        assert_ne!(render(&c, &w), serial);
+    }
 }
@@ -1,5 +1,117 @@
-use crate::color::Color;
+use std::{
+    fmt,
+    io::{self, Write},
+};
 
+use crate::{
+    color::Color,
+    hdr::{self, HdrError},
+    png,
+    tone_map::ToneMap,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PpmFormat {
+    // P3: human readable, but around four times the size.
+    Plain,
+    // P6: one byte per color value.
+    Binary,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PpmError {
+    // Doesn't start with P3 or P6.
+    UnknownFormat(String),
+    // The header, or a P3 value, isn't what it should be.
+    Malformed(String),
+    // The file ends before every pixel has been read.
+    Truncated { expected: usize, found: usize },
+    // A color value is bigger than the maximum given in the header.
+    ValueOutOfRange { value: usize, max: usize },
+}
+
+impl fmt::Display for PpmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PpmError::UnknownFormat(magic) => write!(f, "unknown PPM format {:?}", magic),
+            PpmError::Malformed(message) => write!(f, "malformed PPM: {}", message),
+            PpmError::Truncated { expected, found } => write!(
+                f,
+                "PPM is truncated: expected {} color values but found {}",
+                expected, found
+            ),
+            PpmError::ValueOutOfRange { value, max } => {
+                write!(
+                    f,
+                    "PPM color value {} is greater than the maximum {}",
+                    value, max
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for PpmError {}
+
+// Reads whitespace separated values, skipping comments.
+struct PpmTokens<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> PpmTokens<'a> {
+    fn skip_whitespace_and_comments(&mut self) {
+        while let Some(&c) = self.data.get(self.position) {
+            if c == b'#' {
+                // Comments run to the end of the line.
+                while self.data.get(self.position).is_some_and(|&c| c != b'\n') {
+                    self.position += 1;
+                }
+            } else if c.is_ascii_whitespace() {
+                self.position += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn next_token(&mut self) -> Option<&'a [u8]> {
+        self.skip_whitespace_and_comments();
+        let start = self.position;
+        while self
+            .data
+            .get(self.position)
+            .is_some_and(|c| !c.is_ascii_whitespace() && *c != b'#')
+        {
+            self.position += 1;
+        }
+        (self.position > start).then(|| &self.data[start..self.position])
+    }
+
+    fn next_number(&mut self, what: &str) -> Result<Option<usize>, PpmError> {
+        let Some(token) = self.next_token() else {
+            return Ok(None);
+        };
+        std::str::from_utf8(token)
+            .ok()
+            .and_then(|t| t.parse().ok())
+            .map(Some)
+            .ok_or_else(|| {
+                PpmError::Malformed(format!(
+                    "expected {} but found {:?}",
+                    what,
+                    String::from_utf8_lossy(token)
+                ))
+            })
+    }
+
+    fn header_number(&mut self, what: &str) -> Result<usize, PpmError> {
+        self.next_number(what)?
+            .ok_or_else(|| PpmError::Malformed(format!("missing {}", what)))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Canvas {
     width: usize,
     height: usize,
@@ -13,6 +125,40 @@ fn normalise_color_value(color: &Color) -> [i32; 3] {
     [red.clamp(0, 255), green.clamp(0, 255), blue.clamp(0, 255)]
 }
 
+// One row of pixels as plain PPM text.
+// PPM line limit is 70 chars including the newline, so needs to be wrapped
+fn plain_ppm_row(row: &[Color], tone_map: &ToneMap) -> String {
+    let mut result = String::new();
+    let mut line = String::with_capacity(70);
+    for pixel in row {
+        let values = normalise_color_value(&tone_map.apply(pixel));
+
+        if line.is_empty() {
+            line.push_str(&format!("{} {} {}", values[0], values[1], values[2]));
+            continue;
+        }
+        // Room for all values
+        let full = &format!(" {} {} {}", values[0], values[1], values[2]);
+        if line.len() + full.len() <= 69 {
+            line.push_str(full);
+            continue;
+        }
+        // Line filling up, so add one at a time
+        for v in values.iter() {
+            let sv = format!(" {}", v);
+            if line.len() + sv.len() >= 69 {
+                result.push_str(&line);
+                result.push('\n');
+                line = String::with_capacity(70);
+            }
+            line.push_str(&sv);
+        }
+    }
+    result.push_str(&line);
+    result.push('\n');
+    result
+}
+
 impl Canvas {
     pub fn new(width: usize, height: usize) -> Canvas {
         Canvas {
@@ -22,6 +168,14 @@ impl Canvas {
         }
     }
 
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
     pub fn write_pixel(&mut self, x: usize, y: usize, color: Color) {
         let index = y * self.width + x;
         self.pixels[index] = color;
@@ -32,52 +186,156 @@ impl Canvas {
         &self.pixels[index]
     }
 
+    // An 8-bit RGB PNG of the image, with each pixel tone mapped for display.
+    pub fn to_png(&self, tone_map: &ToneMap) -> Vec<u8> {
+        let rgb: Vec<u8> = self
+            .pixels
+            .iter()
+            .flat_map(|pixel| normalise_color_value(&tone_map.apply(pixel)).map(|v| v as u8))
+            .collect();
+        png::encode(self.width, self.height, &rgb)
+    }
+
+    // Writes a Portable Float Map, keeping colors exactly as they are.
+    pub fn write_pfm<W: Write>(&self, writer: W) -> io::Result<()> {
+        hdr::write_pfm(self, writer)
+    }
+
+    pub fn from_pfm(data: &[u8]) -> Result<Canvas, HdrError> {
+        hdr::read_pfm(data)
+    }
+
+    // Writes a Radiance .hdr file, which is a quarter the size of a PFM but only keeps
+    // around three significant figures.
+    pub fn write_hdr<W: Write>(&self, writer: W) -> io::Result<()> {
+        hdr::write_rgbe(self, writer)
+    }
+
+    pub fn from_hdr(data: &[u8]) -> Result<Canvas, HdrError> {
+        hdr::read_rgbe(data)
+    }
+
+    // Reads a plain (P3) or binary (P6) PPM image. Colors are scaled from 0 to the
+    // file's maximum value down to 0.0 to 1.0.
+    pub fn from_ppm(data: &[u8]) -> Result<Canvas, PpmError> {
+        let mut tokens = PpmTokens { data, position: 0 };
+        let magic = tokens.next_token().unwrap_or_default();
+        let binary = match magic {
+            b"P3" => false,
+            b"P6" => true,
+            _ => {
+                return Err(PpmError::UnknownFormat(
+                    String::from_utf8_lossy(magic).into_owned(),
+                ))
+            }
+        };
+
+        let width = tokens.header_number("width")?;
+        let height = tokens.header_number("height")?;
+        let max = tokens.header_number("maximum color value")?;
+        if max == 0 || max > 65535 {
+            return Err(PpmError::Malformed(format!(
+                "maximum color value {} is not between 1 and 65535",
+                max
+            )));
+        }
+
+        let expected = width
+            .checked_mul(height)
+            .and_then(|n| n.checked_mul(3))
+            .ok_or_else(|| {
+                PpmError::Malformed(format!("{}x{} image is too large", width, height))
+            })?;
+        // Every value takes at least a byte, so a header claiming more than the file holds
+        // can't make us allocate more than the file's size.
+        let remaining = data.len().saturating_sub(tokens.position);
+        let mut values = Vec::with_capacity(expected.min(remaining));
+        if binary {
+            // Exactly one whitespace character separates the header from the pixels.
+            let start = tokens.position + 1;
+            let raw = data.get(start..).unwrap_or_default();
+            // Values take two bytes, most significant first, if they don't fit in one.
+            let bytes_per_value = if max < 256 { 1 } else { 2 };
+            for chunk in raw.chunks_exact(bytes_per_value).take(expected) {
+                values.push(chunk.iter().fold(0, |v, &b| (v << 8) | b as usize));
+            }
+        } else {
+            while values.len() < expected {
+                let Some(value) = tokens.next_number("a color value")? else {
+                    break;
+                };
+                values.push(value);
+            }
+        }
+
+        if values.len() < expected {
+            return Err(PpmError::Truncated {
+                expected,
+                found: values.len(),
+            });
+        }
+
+        let mut canvas = Canvas::new(width, height);
+        for (i, rgb) in values.chunks_exact(3).enumerate() {
+            if let Some(&value) = rgb.iter().find(|&&v| v > max) {
+                return Err(PpmError::ValueOutOfRange { value, max });
+            }
+            let scale = |v: usize| v as f64 / max as f64;
+            canvas.pixels[i] = Color::new(scale(rgb[0]), scale(rgb[1]), scale(rgb[2]));
+        }
+        Ok(canvas)
+    }
+
+    // Plain PPM with colors only clamped, as in the book.
     pub fn to_ppm(&self) -> String {
+        let mut result = vec![];
+        self.write_ppm(&mut result, PpmFormat::Plain, &ToneMap::default())
+            .expect("writing to a Vec can't fail");
+        String::from_utf8(result).expect("plain PPM is ASCII")
+    }
+
+    // Writes the image a row at a time, so large images never need to be held in memory
+    // twice. Wrap files in a BufWriter, as plain PPM writes lots of small pieces.
+    // Each pixel is tone mapped for display as it is written.
+    pub fn write_ppm<W: Write>(
+        &self,
+        mut writer: W,
+        format: PpmFormat,
+        tone_map: &ToneMap,
+    ) -> io::Result<()> {
         // PPM format:
-        // first line is version: always "P3"
+        // first line is version: "P3" for plain text or "P6" for binary
         // second line is "width space height"
         // third line is the max colour value: 255
         // Next lines are the pixels in RGB order
-        //
-        // PPM line limit is 70 chars including the newline, so needs to be wrapped
-        let mut result = format!("P3\n{} {}\n255\n", self.width, self.height);
-        for y in 0..self.height {
-            let mut line = String::with_capacity(70);
-            for x in 0..self.width {
-                let index = y * self.width + x;
-                let pixel = &self.pixels[index];
-                let values = normalise_color_value(pixel);
-
-                if line.is_empty() {
-                    line.push_str(&format!("{} {} {}", values[0], values[1], values[2]));
-                    continue;
-                }
-                // Room for all values
-                let full = &format!(" {} {} {}", values[0], values[1], values[2]);
-                if line.len() + full.len() <= 69 {
-                    line.push_str(full);
-                    continue;
-                }
-                // Line filling up, so add one at a time
-                for v in values.iter() {
-                    let sv = format!(" {}", v);
-                    if line.len() + sv.len() >= 69 {
-                        result.push_str(&line);
-                        result.push('\n');
-                        line = String::with_capacity(70);
-                    }
-                    line.push_str(&sv);
+        let magic = match format {
+            PpmFormat::Plain => "P3",
+            PpmFormat::Binary => "P6",
+        };
+        write!(writer, "{}\n{} {}\n255\n", magic, self.width, self.height)?;
+
+        for row in self.pixels.chunks(self.width.max(1)) {
+            match format {
+                PpmFormat::Plain => writer.write_all(plain_ppm_row(row, tone_map).as_bytes())?,
+                PpmFormat::Binary => {
+                    let bytes: Vec<u8> = row
+                        .iter()
+                        .flat_map(|pixel| {
+                            normalise_color_value(&tone_map.apply(pixel)).map(|v| v as u8)
+                        })
+                        .collect();
+                    writer.write_all(&bytes)?;
                 }
             }
-            result.push_str(&line);
-            result.push('\n');
         }
-        result
+        writer.flush()
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::tone_map::{ToneMapOperator, Transfer};
+
     use super::*;
 
     #[test]
@@ -95,7 +353,7 @@ mod tests {
     fn writing_pixels_to_a_canvas() {
         let mut c = Canvas::new(10, 20);
         let red = Color::new(1.0, 0.0, 0.0);
-        c.write_pixel(2, 3, red.clone());
+        c.write_pixel(2, 3, red);
         assert_eq!(*(c.pixel_at(2, 3)), red);
     }
 
@@ -156,4 +414,238 @@ mod tests {
         let ppm = c.to_ppm();
         assert_eq!(ppm.chars().last().unwrap(), '\n');
     }
+
+    #[test]
+    fn reading_a_file_with_the_wrong_magic_number() {
+        let ppm = "P32\n1 1\n255\n0 0 0\n";
+        assert_eq!(
+            Canvas::from_ppm(ppm.as_bytes()),
+            Err(PpmError::UnknownFormat("P32".to_string()))
+        );
+    }
+
+    #[test]
+    fn reading_a_plain_ppm_returns_a_canvas() {
+        let ppm = "P3\n10 2\n255\n\
+                   0 0 0  0 0 0  0 0 0  0 0 0  0 0 0  0 0 0  0 0 0  0 0 0  0 0 0  0 0 0\n\
+                   0 0 0  0 0 0  0 0 0  0 0 0  0 0 0  0 0 0  0 0 0  0 0 0  0 0 0  0 0 0\n";
+        let canvas = Canvas::from_ppm(ppm.as_bytes()).unwrap();
+        assert_eq!(canvas.width(), 10);
+        assert_eq!(canvas.height(), 2);
+    }
+
+    #[test]
+    fn reading_pixel_data_from_a_ppm_file() {
+        let ppm = "P3\n4 3\n255\n\
+                   255 127 0  0 127 255  127 255 0  255 255 255\n\
+                   0 0 0  255 0 0  0 255 0  0 0 255\n\
+                   255 255 0  0 255 255  255 0 255  127 127 127\n";
+        let canvas = Canvas::from_ppm(ppm.as_bytes()).unwrap();
+        let examples = [
+            (0, 0, Color::new(1.0, 0.49804, 0.0)),
+            (1, 0, Color::new(0.0, 0.49804, 1.0)),
+            (2, 0, Color::new(0.49804, 1.0, 0.0)),
+            (3, 0, Color::new(1.0, 1.0, 1.0)),
+            (0, 1, Color::new(0.0, 0.0, 0.0)),
+            (1, 1, Color::new(1.0, 0.0, 0.0)),
+            (2, 1, Color::new(0.0, 1.0, 0.0)),
+            (3, 1, Color::new(0.0, 0.0, 1.0)),
+            (0, 2, Color::new(1.0, 1.0, 0.0)),
+            (1, 2, Color::new(0.0, 1.0, 1.0)),
+            (2, 2, Color::new(1.0, 0.0, 1.0)),
+            (3, 2, Color::new(0.49804, 0.49804, 0.49804)),
+        ];
+        for (x, y, expected) in examples {
+            assert_eq!(canvas.pixel_at(x, y), &expected);
+        }
+    }
+
+    #[test]
+    fn ppm_parsing_ignores_comment_lines() {
+        let ppm = "P3\n# this is a comment\n2 1\n# this, too\n255\n\
+                   # another comment\n255 255 255\n# oh, no, comments in the pixel data!\n\
+                   255 0 255\n";
+        let canvas = Canvas::from_ppm(ppm.as_bytes()).unwrap();
+        assert_eq!(canvas.pixel_at(0, 0), &Color::new(1.0, 1.0, 1.0));
+        assert_eq!(canvas.pixel_at(1, 0), &Color::new(1.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn ppm_parsing_allows_rgb_triple_to_span_lines() {
+        let ppm = "P3\n1 1\n255\n51\n153\n\n204\n";
+        let canvas = Canvas::from_ppm(ppm.as_bytes()).unwrap();
+        assert_eq!(canvas.pixel_at(0, 0), &Color::new(0.2, 0.6, 0.8));
+    }
+
+    #[test]
+    fn ppm_parsing_respects_the_scale_setting() {
+        let ppm = "P3 2 2 100\n100 100 100  50 50 50\n75 50 25  0 0 0\n";
+        let canvas = Canvas::from_ppm(ppm.as_bytes()).unwrap();
+        assert_eq!(canvas.pixel_at(0, 1), &Color::new(0.75, 0.5, 0.25));
+    }
+
+    #[test]
+    fn reading_a_binary_ppm() {
+        let mut ppm = b"P6\n# binary\n2 1\n255\n".to_vec();
+        ppm.extend([255, 0, 51, 0, 102, 255]);
+        let canvas = Canvas::from_ppm(&ppm).unwrap();
+        assert_eq!(canvas.pixel_at(0, 0), &Color::new(1.0, 0.0, 0.2));
+        assert_eq!(canvas.pixel_at(1, 0), &Color::new(0.0, 0.4, 1.0));
+    }
+
+    #[test]
+    fn reading_a_binary_ppm_with_two_byte_values() {
+        let mut ppm = b"P6 1 1 1000\n".to_vec();
+        ppm.extend([0x03, 0xe8, 0x01, 0xf4, 0x00, 0x00]);
+        let canvas = Canvas::from_ppm(&ppm).unwrap();
+        assert_eq!(canvas.pixel_at(0, 0), &Color::new(1.0, 0.5, 0.0));
+    }
+
+    #[test]
+    fn reading_truncated_ppm_files() {
+        let ppm = "P3\n2 1\n255\n255 255 255 0\n";
+        assert_eq!(
+            Canvas::from_ppm(ppm.as_bytes()),
+            Err(PpmError::Truncated {
+                expected: 6,
+                found: 4
+            })
+        );
+
+        let mut ppm = b"P6\n2 1\n255\n".to_vec();
+        ppm.extend([255, 0, 51]);
+        assert_eq!(
+            Canvas::from_ppm(&ppm),
+            Err(PpmError::Truncated {
+                expected: 6,
+                found: 3
+            })
+        );
+    }
+
+    #[test]
+    fn reading_ppm_files_claiming_huge_sizes() {
+        let ppm = "P6\n100000 100000\n255\n";
+        assert_eq!(
+            Canvas::from_ppm(ppm.as_bytes()),
+            Err(PpmError::Truncated {
+                expected: 30_000_000_000,
+                found: 0
+            })
+        );
+
+        let ppm = format!("P6\n{} 2\n255\n", usize::MAX);
+        assert!(matches!(
+            Canvas::from_ppm(ppm.as_bytes()),
+            Err(PpmError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn reading_malformed_ppm_files() {
+        let examples = [
+            "P3\n",
+            "P3\nwide 1\n255\n",
+            "P3\n1 1\n0\n0 0 0\n",
+            "P3\n1 1\n255\n0 zero 0\n",
+        ];
+        for ppm in examples {
+            assert!(matches!(
+                Canvas::from_ppm(ppm.as_bytes()),
+                Err(PpmError::Malformed(_))
+            ));
+        }
+
+        let ppm = "P3\n1 1\n255\n0 256 0\n";
+        assert_eq!(
+            Canvas::from_ppm(ppm.as_bytes()),
+            Err(PpmError::ValueOutOfRange {
+                value: 256,
+                max: 255
+            })
+        );
+    }
+
+    #[test]
+    fn ppm_round_trip() {
+        let mut c = Canvas::new(3, 2);
+        c.write_pixel(0, 0, Color::new(1.0, 0.0, 0.2));
+        c.write_pixel(2, 1, Color::new(0.4, 0.6, 0.8));
+        let read = Canvas::from_ppm(c.to_ppm().as_bytes()).unwrap();
+        assert_eq!(read, c);
+    }
+
+    #[test]
+    fn writing_a_binary_ppm() {
+        let mut c = Canvas::new(2, 2);
+        c.write_pixel(0, 0, Color::new(1.5, 0.0, 0.0));
+        c.write_pixel(1, 1, Color::new(0.0, 0.5, -1.0));
+        let mut ppm = vec![];
+        c.write_ppm(&mut ppm, PpmFormat::Binary, &ToneMap::default())
+            .unwrap();
+        let header = b"P6\n2 2\n255\n";
+        assert_eq!(&ppm[..header.len()], header);
+        assert_eq!(
+            &ppm[header.len()..],
+            &[255, 0, 0, 0, 0, 0, 0, 0, 0, 0, 128, 0]
+        );
+    }
+
+    #[test]
+    fn streamed_plain_ppm_matches_to_ppm() {
+        let mut c = Canvas::new(10, 2);
+        for y in 0..2 {
+            for x in 0..10 {
+                c.write_pixel(x, y, Color::new(1.0, 0.8, 0.6));
+            }
+        }
+        let mut ppm = vec![];
+        c.write_ppm(&mut ppm, PpmFormat::Plain, &ToneMap::default())
+            .unwrap();
+        assert_eq!(String::from_utf8(ppm).unwrap(), c.to_ppm());
+    }
+
+    #[test]
+    fn binary_ppm_round_trip() {
+        let mut c = Canvas::new(3, 2);
+        c.write_pixel(0, 0, Color::new(1.0, 0.0, 0.2));
+        c.write_pixel(2, 1, Color::new(0.4, 0.6, 0.8));
+        let mut ppm = vec![];
+        c.write_ppm(&mut ppm, PpmFormat::Binary, &ToneMap::default())
+            .unwrap();
+        assert_eq!(Canvas::from_ppm(&ppm).unwrap(), c);
+    }
+
+    #[test]
+    fn png_starts_with_signature_and_header() {
+        let c = Canvas::new(4, 2);
+        let png = c.to_png(&ToneMap::default());
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..24], &[0, 0, 0, 4, 0, 0, 0, 2]);
+        assert_eq!(&png[png.len() - 12..png.len() - 8], &[0, 0, 0, 0]);
+        assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+    }
+
+    #[test]
+    fn tone_map_is_applied_to_8_bit_formats_only() {
+        let mut c = Canvas::new(1, 1);
+        c.write_pixel(0, 0, Color::new(0.5, 1.0, 3.0));
+        let tone_map = ToneMap::default()
+            .operator(ToneMapOperator::Reinhard)
+            .transfer(Transfer::Gamma(2.0));
+
+        let mut ppm = vec![];
+        c.write_ppm(&mut ppm, PpmFormat::Plain, &tone_map).unwrap();
+        assert_eq!(ppm, b"P3\n1 1\n255\n147 180 221\n");
+        assert_eq!(c.to_ppm(), "P3\n1 1\n255\n128 255 255\n");
+        assert_ne!(c.to_png(&tone_map), c.to_png(&ToneMap::default()));
+
+        let mut pfm = vec![];
+        c.write_pfm(&mut pfm).unwrap();
+        assert_eq!(
+            *Canvas::from_pfm(&pfm).unwrap().pixel_at(0, 0),
+            Color::new(0.5, 1.0, 3.0)
+        );
+    }
 }
@@ -76,7 +76,11 @@ impl Mul for &Color {
 impl Mul<f64> for Color {
     type Output = Self;
     fn mul(self, rhs: f64) -> Self::Output {
-        &self * rhs
+        Self {
+            red: self.red * rhs,
+            green: self.green * rhs,
+            blue: self.blue * rhs,
+        }
     }
 }
 
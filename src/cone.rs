@@ -0,0 +1,215 @@
+use crate::{
+    bounds::BoundingBox,
+    cylinder::intersect_caps,
+    intersections::{intersection, Intersection},
+    matrix::EPSILON,
+    rays::Ray,
+    shape::{Shape, ShapeType},
+    tuple::{point, vector, Tuple},
+};
+
+pub struct Cone {}
+
+impl Cone {
+    pub fn local_intersect(s: &Shape, ray: &Ray) -> Vec<Intersection> {
+        let ShapeType::Cone {
+            minimum,
+            maximum,
+            closed,
+        } = s.shape_type
+        else {
+            unreachable!("not a cone")
+        };
+
+        let mut xs = vec![];
+
+        let (o, d) = (&ray.origin, &ray.direction);
+        let a = d.x.powi(2) - d.y.powi(2) + d.z.powi(2);
+        let b = 2.0 * o.x * d.x - 2.0 * o.y * d.y + 2.0 * o.z * d.z;
+        let c = o.x.powi(2) - o.y.powi(2) + o.z.powi(2);
+
+        let mut ts = vec![];
+        if a.abs() < EPSILON {
+            // Parallel to one of the cone's halves, so it can only hit the other half once.
+            if b.abs() >= EPSILON {
+                ts.push(-c / (2.0 * b));
+            }
+        } else {
+            let discriminant = b * b - 4.0 * a * c;
+            // Clamp tiny negative values so rays grazing the apex still register a hit.
+            let discriminant = if discriminant.abs() < EPSILON {
+                0.0
+            } else {
+                discriminant
+            };
+            if discriminant >= 0.0 {
+                let t0 = (-b - discriminant.sqrt()) / (2.0 * a);
+                let t1 = (-b + discriminant.sqrt()) / (2.0 * a);
+                let (t0, t1) = if t0 > t1 { (t1, t0) } else { (t0, t1) };
+                ts.push(t0);
+                ts.push(t1);
+            }
+        }
+
+        for t in ts {
+            let y = o.y + t * d.y;
+            if minimum < y && y < maximum {
+                xs.push(intersection(t, s));
+            }
+        }
+
+        if closed {
+            // The radius of a cone at any y is the absolute value of that y.
+            intersect_caps(s, ray, minimum, maximum, f64::abs, &mut xs);
+        }
+        xs
+    }
+
+    pub fn local_normal(shape: &Shape, pt: &Tuple) -> Tuple {
+        let ShapeType::Cone {
+            minimum, maximum, ..
+        } = shape.shape_type
+        else {
+            unreachable!("not a cone")
+        };
+
+        let dist = pt.x.powi(2) + pt.z.powi(2);
+        if dist < maximum.powi(2) && pt.y >= maximum - EPSILON {
+            return vector(0.0, 1.0, 0.0);
+        }
+        if dist < minimum.powi(2) && pt.y <= minimum + EPSILON {
+            return vector(0.0, -1.0, 0.0);
+        }
+
+        let y = dist.sqrt();
+        let y = if pt.y > 0.0 { -y } else { y };
+        vector(pt.x, y, pt.z)
+    }
+
+    pub fn bounds(shape: &Shape) -> BoundingBox {
+        let ShapeType::Cone {
+            minimum, maximum, ..
+        } = shape.shape_type
+        else {
+            unreachable!("not a cone")
+        };
+
+        // The cone is widest at whichever end is furthest from the apex.
+        let limit = minimum.abs().max(maximum.abs());
+        BoundingBox::new(point(-limit, minimum, -limit), point(limit, maximum, limit))
+    }
+}
+
+pub fn cone() -> Shape {
+    Shape::new(ShapeType::Cone {
+        minimum: f64::NEG_INFINITY,
+        maximum: f64::INFINITY,
+        closed: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{rays::ray, tuple::normalise};
+
+    use super::*;
+
+    #[test]
+    fn intersecting_a_cone_with_a_ray() {
+        let shape = cone();
+        let examples = [
+            (point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0), 5.0, 5.0),
+            (
+                point(0.0, 0.0, -5.0),
+                vector(1.0, 1.0, 1.0),
+                8.66025,
+                8.66025,
+            ),
+            (
+                point(1.0, 1.0, -5.0),
+                vector(-0.5, -1.0, 1.0),
+                4.55006,
+                49.44994,
+            ),
+        ];
+        for (origin, direction, t0, t1) in examples {
+            let r = ray(origin, normalise(&direction));
+            let xs = shape.intersect(&r);
+            assert_eq!(xs.len(), 2);
+            assert!((xs[0].t - t0).abs() < 0.0001);
+            assert!((xs[1].t - t1).abs() < 0.0001);
+        }
+    }
+
+    #[test]
+    fn intersecting_a_cone_with_ray_parallel_to_one_half() {
+        let shape = cone();
+        let r = ray(point(0.0, 0.0, -1.0), normalise(&vector(0.0, 1.0, 1.0)));
+        let xs = shape.intersect(&r);
+        assert_eq!(xs.len(), 1);
+        assert!((xs[0].t - 0.35355).abs() < EPSILON);
+    }
+
+    #[test]
+    fn intersecting_the_end_caps_of_a_cone() {
+        let shape = Shape::new(ShapeType::Cone {
+            minimum: -0.5,
+            maximum: 0.5,
+            closed: true,
+        });
+        let examples = [
+            (point(0.0, 0.0, -5.0), vector(0.0, 1.0, 0.0), 0),
+            (point(0.0, 0.0, -0.25), vector(0.0, 1.0, 1.0), 2),
+            (point(0.0, 0.0, -0.25), vector(0.0, 1.0, 0.0), 4),
+        ];
+        for (origin, direction, count) in examples {
+            let r = ray(origin, normalise(&direction));
+            let xs = shape.intersect(&r);
+            assert_eq!(xs.len(), count);
+        }
+    }
+
+    #[test]
+    fn bounding_box_of_cones() {
+        assert!(!cone().bounds().is_finite());
+
+        let shape = Shape::new(ShapeType::Cone {
+            minimum: -5.0,
+            maximum: 3.0,
+            closed: true,
+        });
+        let b = shape.bounds();
+        assert_eq!(b.min, point(-5.0, -5.0, -5.0));
+        assert_eq!(b.max, point(5.0, 3.0, 5.0));
+    }
+
+    #[test]
+    fn computing_the_normal_on_a_cone() {
+        let shape = cone();
+        let examples = [
+            (point(0.0, 0.0, 0.0), vector(0.0, 0.0, 0.0)),
+            (point(1.0, 1.0, 1.0), vector(1.0, -(2.0_f64.sqrt()), 1.0)),
+            (point(-1.0, -1.0, 0.0), vector(-1.0, 1.0, 0.0)),
+        ];
+        for (p, expected) in examples {
+            assert_eq!(Cone::local_normal(&shape, &p), expected);
+        }
+    }
+
+    #[test]
+    fn normal_on_the_end_caps_of_a_cone() {
+        let shape = Shape::new(ShapeType::Cone {
+            minimum: -1.0,
+            maximum: 1.0,
+            closed: true,
+        });
+        assert_eq!(
+            shape.normal_at(&point(0.5, 1.0, 0.0)),
+            vector(0.0, 1.0, 0.0)
+        );
+        assert_eq!(
+            shape.normal_at(&point(0.0, -1.0, 0.5)),
+            vector(0.0, -1.0, 0.0)
+        );
+    }
+}
@@ -0,0 +1,238 @@
+use crate::{
+    bounds::BoundingBox,
+    intersections::Intersection,
+    rays::Ray,
+    shape::{Shape, ShapeType},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CsgOperation {
+    Union,
+    Intersection,
+    Difference,
+}
+
+pub struct Csg {}
+
+impl Csg {
+    pub fn local_intersect(s: &Shape, ray: &Ray) -> Vec<Intersection> {
+        let ShapeType::Csg { left, right, .. } = &s.shape_type else {
+            unreachable!("not a CSG shape")
+        };
+
+        let mut xs = left.intersect(ray);
+        xs.extend(right.intersect(ray));
+        xs.sort_by(|a, b| a.t.total_cmp(&b.t));
+        filter_intersections(s, xs)
+    }
+
+    pub fn bounds(s: &Shape) -> BoundingBox {
+        let ShapeType::Csg { left, right, .. } = &s.shape_type else {
+            unreachable!("not a CSG shape")
+        };
+
+        let mut result = left.parent_space_bounds();
+        result.merge(&right.parent_space_bounds());
+        result
+    }
+}
+
+// Decides whether a hit on one child is part of the combined surface.
+// lhit is true if the left shape was hit, inl/inr are whether the ray is currently
+// inside the left/right shape.
+pub fn intersection_allowed(op: CsgOperation, lhit: bool, inl: bool, inr: bool) -> bool {
+    match op {
+        CsgOperation::Union => (lhit && !inr) || (!lhit && !inl),
+        CsgOperation::Intersection => (lhit && inr) || (!lhit && inl),
+        CsgOperation::Difference => (lhit && !inr) || (!lhit && inl),
+    }
+}
+
+// Keeps only the intersections that lie on the surface of the combined shape.
+// Expects the intersections to be sorted.
+pub fn filter_intersections(s: &Shape, xs: Vec<Intersection>) -> Vec<Intersection> {
+    let ShapeType::Csg {
+        operation, left, ..
+    } = &s.shape_type
+    else {
+        unreachable!("not a CSG shape")
+    };
+
+    // Start outside of both children.
+    let mut inl = false;
+    let mut inr = false;
+    let mut result = vec![];
+
+    for i in xs {
+        let lhit = left.includes(&i.object);
+
+        if intersection_allowed(*operation, lhit, inl, inr) {
+            result.push(i);
+        }
+
+        // Every hit on a child either enters or leaves it.
+        if lhit {
+            inl = !inl;
+        } else {
+            inr = !inr;
+        }
+    }
+
+    result
+}
+
+pub fn csg(operation: CsgOperation, left: Shape, right: Shape) -> Shape {
+    Shape::new(ShapeType::Csg {
+        operation,
+        left: Box::new(left),
+        right: Box::new(right),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        cube::cube,
+        intersections::{hit, intersection, prepare_computations},
+        matrix::Matrix,
+        rays::ray,
+        sphere::sphere,
+        transformations::{scaling, translation},
+        tuple::{point, vector},
+    };
+
+    use super::*;
+
+    fn children(s: &Shape) -> (&Shape, &Shape) {
+        match &s.shape_type {
+            ShapeType::Csg { left, right, .. } => (left, right),
+            _ => panic!("not a CSG shape"),
+        }
+    }
+
+    #[test]
+    fn csg_is_created_with_an_operation_and_two_shapes() {
+        let s1 = sphere();
+        let s2 = cube();
+        let c = csg(CsgOperation::Union, s1.clone(), s2.clone());
+        let (left, right) = children(&c);
+        assert_eq!(*left, s1);
+        assert_eq!(*right, s2);
+        assert_eq!(left.parents(), &[Matrix::identity_4x4()]);
+        assert_eq!(right.parents(), &[Matrix::identity_4x4()]);
+    }
+
+    #[test]
+    fn evaluating_the_rule_for_a_csg_operation() {
+        use CsgOperation::*;
+        let examples = [
+            (Union, true, true, true, false),
+            (Union, true, true, false, true),
+            (Union, true, false, true, false),
+            (Union, true, false, false, true),
+            (Union, false, true, true, false),
+            (Union, false, true, false, false),
+            (Union, false, false, true, true),
+            (Union, false, false, false, true),
+            (Intersection, true, true, true, true),
+            (Intersection, true, true, false, false),
+            (Intersection, true, false, true, true),
+            (Intersection, true, false, false, false),
+            (Intersection, false, true, true, true),
+            (Intersection, false, true, false, true),
+            (Intersection, false, false, true, false),
+            (Intersection, false, false, false, false),
+            (Difference, true, true, true, false),
+            (Difference, true, true, false, true),
+            (Difference, true, false, true, false),
+            (Difference, true, false, false, true),
+            (Difference, false, true, true, true),
+            (Difference, false, true, false, true),
+            (Difference, false, false, true, false),
+            (Difference, false, false, false, false),
+        ];
+        for (op, lhit, inl, inr, expected) in examples {
+            assert_eq!(intersection_allowed(op, lhit, inl, inr), expected);
+        }
+    }
+
+    #[test]
+    fn filtering_a_list_of_intersections() {
+        let examples = [
+            (CsgOperation::Union, 0, 3),
+            (CsgOperation::Intersection, 1, 2),
+            (CsgOperation::Difference, 0, 1),
+        ];
+        for (op, x0, x1) in examples {
+            let c = csg(op, sphere(), cube());
+            let (s1, s2) = children(&c);
+            let xs = vec![
+                intersection(1.0, s1),
+                intersection(2.0, s2),
+                intersection(3.0, s1),
+                intersection(4.0, s2),
+            ];
+            let result = filter_intersections(&c, xs.clone());
+            assert_eq!(result.len(), 2);
+            assert_eq!(result[0], xs[x0]);
+            assert_eq!(result[1], xs[x1]);
+        }
+    }
+
+    #[test]
+    fn csg_has_a_bounding_box_that_contains_its_children() {
+        let left = sphere();
+        let mut right = sphere();
+        right.set_transform(translation(2.0, 3.0, 4.0));
+        let shape = csg(CsgOperation::Difference, left, right);
+        let b = shape.bounds();
+        assert_eq!(b.min, point(-1.0, -1.0, -1.0));
+        assert_eq!(b.max, point(3.0, 4.0, 5.0));
+    }
+
+    #[test]
+    fn ray_misses_a_csg_object() {
+        let c = csg(CsgOperation::Union, sphere(), cube());
+        let r = ray(point(0.0, 2.0, -5.0), vector(0.0, 0.0, 1.0));
+        assert!(c.intersect(&r).is_empty());
+    }
+
+    #[test]
+    fn ray_hits_a_csg_object() {
+        let s1 = sphere();
+        let mut s2 = sphere();
+        s2.set_transform(translation(0.0, 0.0, 0.5));
+        let c = csg(CsgOperation::Union, s1.clone(), s2.clone());
+        let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let xs = c.intersect(&r);
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].t, 4.0);
+        assert_eq!(xs[0].object, s1);
+        assert_eq!(xs[1].t, 6.5);
+        assert_eq!(xs[1].object, s2);
+    }
+
+    #[test]
+    fn hit_on_transformed_csg_uses_the_child_normal() {
+        let mut big_cube = cube();
+        big_cube.set_transform(scaling(2.0, 2.0, 2.0));
+        let mut c = csg(CsgOperation::Difference, sphere(), big_cube);
+        c.set_transform(translation(0.0, 0.0, 2.0));
+        let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let xs = c.intersect(&r);
+        // The cube removes the whole sphere.
+        assert!(hit(&xs).is_none());
+
+        let mut small_sphere = sphere();
+        small_sphere.set_transform(scaling(0.5, 0.5, 0.5));
+        let mut c = csg(CsgOperation::Difference, cube(), small_sphere);
+        c.set_transform(translation(0.0, 0.0, 2.0));
+        let xs = c.intersect(&r);
+        assert_eq!(xs.len(), 4);
+        let i = hit(&xs).unwrap();
+        assert_eq!(i.t, 6.0);
+        let comps = prepare_computations(i, &r, &xs);
+        assert_eq!(comps.point, point(0.0, 0.0, 1.0));
+        assert_eq!(comps.normalv, vector(0.0, 0.0, -1.0));
+    }
+}
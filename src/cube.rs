@@ -0,0 +1,159 @@
+use crate::{
+    bounds::BoundingBox,
+    intersections::{intersection, Intersection},
+    matrix::EPSILON,
+    rays::Ray,
+    shape::{Shape, ShapeType},
+    tuple::{point, vector, Tuple},
+};
+
+pub struct Cube {}
+
+impl Cube {
+    pub fn local_intersect(s: &Shape, ray: &Ray) -> Vec<Intersection> {
+        // The cube is treated as three pairs of parallel planes (slabs), one per axis.
+        // The ray is inside the cube where it is inside all three slabs at once.
+        let (xtmin, xtmax) = check_axis(ray.origin.x, ray.direction.x);
+        let (ytmin, ytmax) = check_axis(ray.origin.y, ray.direction.y);
+        let (ztmin, ztmax) = check_axis(ray.origin.z, ray.direction.z);
+
+        let tmin = xtmin.max(ytmin).max(ztmin);
+        let tmax = xtmax.min(ytmax).min(ztmax);
+
+        if tmin > tmax {
+            return vec![];
+        }
+
+        vec![intersection(tmin, s), intersection(tmax, s)]
+    }
+
+    pub fn local_normal(_shape: &Shape, pt: &Tuple) -> Tuple {
+        // The normal points along whichever axis the point is furthest out on.
+        let maxc = pt.x.abs().max(pt.y.abs()).max(pt.z.abs());
+
+        if maxc == pt.x.abs() {
+            vector(pt.x, 0.0, 0.0)
+        } else if maxc == pt.y.abs() {
+            vector(0.0, pt.y, 0.0)
+        } else {
+            vector(0.0, 0.0, pt.z)
+        }
+    }
+
+    pub fn bounds(_shape: &Shape) -> BoundingBox {
+        BoundingBox::new(point(-1.0, -1.0, -1.0), point(1.0, 1.0, 1.0))
+    }
+}
+
+// Returns where the ray enters and leaves the slab between -1 and 1 on one axis.
+fn check_axis(origin: f64, direction: f64) -> (f64, f64) {
+    let tmin_numerator = -1.0 - origin;
+    let tmax_numerator = 1.0 - origin;
+
+    let (tmin, tmax) = if direction.abs() >= EPSILON {
+        (tmin_numerator / direction, tmax_numerator / direction)
+    } else {
+        // Parallel to the slab, so either always inside it or never.
+        (
+            tmin_numerator * f64::INFINITY,
+            tmax_numerator * f64::INFINITY,
+        )
+    };
+
+    if tmin > tmax {
+        (tmax, tmin)
+    } else {
+        (tmin, tmax)
+    }
+}
+
+pub fn cube() -> Shape {
+    Shape::new(ShapeType::Cube)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rays::ray;
+
+    use super::*;
+
+    #[test]
+    fn cube_has_a_bounding_box() {
+        let b = cube().bounds();
+        assert_eq!(b.min, point(-1.0, -1.0, -1.0));
+        assert_eq!(b.max, point(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn ray_intersects_a_cube() {
+        let c = cube();
+        let examples = [
+            // +x
+            (point(5.0, 0.5, 0.0), vector(-1.0, 0.0, 0.0), 4.0, 6.0),
+            // -x
+            (point(-5.0, 0.5, 0.0), vector(1.0, 0.0, 0.0), 4.0, 6.0),
+            // +y
+            (point(0.5, 5.0, 0.0), vector(0.0, -1.0, 0.0), 4.0, 6.0),
+            // -y
+            (point(0.5, -5.0, 0.0), vector(0.0, 1.0, 0.0), 4.0, 6.0),
+            // +z
+            (point(0.5, 0.0, 5.0), vector(0.0, 0.0, -1.0), 4.0, 6.0),
+            // -z
+            (point(0.5, 0.0, -5.0), vector(0.0, 0.0, 1.0), 4.0, 6.0),
+            // inside
+            (point(0.0, 0.5, 0.0), vector(0.0, 0.0, 1.0), -1.0, 1.0),
+        ];
+        for (origin, direction, t1, t2) in examples {
+            let r = ray(origin, direction);
+            let xs = c.intersect(&r);
+            assert_eq!(xs.len(), 2);
+            assert_eq!(xs[0].t, t1);
+            assert_eq!(xs[1].t, t2);
+        }
+    }
+
+    #[test]
+    fn ray_misses_a_cube() {
+        let c = cube();
+        let examples = [
+            (point(-2.0, 0.0, 0.0), vector(0.2673, 0.5345, 0.8018)),
+            (point(0.0, -2.0, 0.0), vector(0.8018, 0.2673, 0.5345)),
+            (point(0.0, 0.0, -2.0), vector(0.5345, 0.8018, 0.2673)),
+            (point(2.0, 0.0, 2.0), vector(0.0, 0.0, -1.0)),
+            (point(0.0, 2.0, 2.0), vector(0.0, -1.0, 0.0)),
+            (point(2.0, 2.0, 0.0), vector(-1.0, 0.0, 0.0)),
+        ];
+        for (origin, direction) in examples {
+            let r = ray(origin, direction);
+            let xs = c.intersect(&r);
+            assert!(xs.is_empty());
+        }
+    }
+
+    #[test]
+    fn ray_parallel_to_faces_outside_cube_misses() {
+        let c = cube();
+        let r = ray(point(1.5, 1.5, -5.0), vector(0.0, 0.0, 1.0));
+        let xs = c.intersect(&r);
+        assert!(xs.is_empty());
+    }
+
+    #[test]
+    fn normal_on_surface_of_a_cube() {
+        let c = cube();
+        let examples = [
+            (point(1.0, 0.5, -0.8), vector(1.0, 0.0, 0.0)),
+            (point(-1.0, -0.2, 0.9), vector(-1.0, 0.0, 0.0)),
+            (point(-0.4, 1.0, -0.1), vector(0.0, 1.0, 0.0)),
+            (point(0.3, -1.0, -0.7), vector(0.0, -1.0, 0.0)),
+            (point(-0.6, 0.3, 1.0), vector(0.0, 0.0, 1.0)),
+            (point(0.4, 0.4, -1.0), vector(0.0, 0.0, -1.0)),
+            (point(1.0, 1.0, 1.0), vector(1.0, 0.0, 0.0)),
+            (point(-1.0, -1.0, -1.0), vector(-1.0, 0.0, 0.0)),
+        ];
+        for (p, expected) in examples {
+            let normal = c.normal_at(&p);
+            assert_eq!(normal, expected);
+        }
+    }
+}
@@ -0,0 +1,298 @@
+use std::f64::consts::PI;
+
+use crate::{
+    bounds::BoundingBox,
+    intersections::{intersection, Intersection},
+    matrix::EPSILON,
+    rays::Ray,
+    shape::{Shape, ShapeType},
+    tuple::{point, vector, Tuple},
+};
+
+pub struct Cylinder {}
+
+impl Cylinder {
+    pub fn local_intersect(s: &Shape, ray: &Ray) -> Vec<Intersection> {
+        let ShapeType::Cylinder {
+            minimum,
+            maximum,
+            closed,
+        } = s.shape_type
+        else {
+            unreachable!("not a cylinder")
+        };
+
+        let mut xs = vec![];
+
+        // A ray parallel to the y axis can only hit the caps.
+        let a = ray.direction.x.powi(2) + ray.direction.z.powi(2);
+        if a.abs() >= EPSILON {
+            let b = 2.0 * ray.origin.x * ray.direction.x + 2.0 * ray.origin.z * ray.direction.z;
+            let c = ray.origin.x.powi(2) + ray.origin.z.powi(2) - 1.0;
+            let discriminant = b * b - 4.0 * a * c;
+            if discriminant < 0.0 {
+                return xs;
+            }
+
+            let t0 = (-b - discriminant.sqrt()) / (2.0 * a);
+            let t1 = (-b + discriminant.sqrt()) / (2.0 * a);
+            let (t0, t1) = if t0 > t1 { (t1, t0) } else { (t0, t1) };
+
+            // Only keep the hits that lie between the truncation bounds.
+            for t in [t0, t1] {
+                let y = ray.origin.y + t * ray.direction.y;
+                if minimum < y && y < maximum {
+                    xs.push(intersection(t, s));
+                }
+            }
+        }
+
+        if closed {
+            intersect_caps(s, ray, minimum, maximum, |_| 1.0, &mut xs);
+        }
+        xs
+    }
+
+    pub fn local_normal(shape: &Shape, pt: &Tuple) -> Tuple {
+        let ShapeType::Cylinder {
+            minimum, maximum, ..
+        } = shape.shape_type
+        else {
+            unreachable!("not a cylinder")
+        };
+
+        let dist = pt.x.powi(2) + pt.z.powi(2);
+        if dist < 1.0 && pt.y >= maximum - EPSILON {
+            vector(0.0, 1.0, 0.0)
+        } else if dist < 1.0 && pt.y <= minimum + EPSILON {
+            vector(0.0, -1.0, 0.0)
+        } else {
+            vector(pt.x, 0.0, pt.z)
+        }
+    }
+
+    pub fn bounds(shape: &Shape) -> BoundingBox {
+        let ShapeType::Cylinder {
+            minimum, maximum, ..
+        } = shape.shape_type
+        else {
+            unreachable!("not a cylinder")
+        };
+
+        BoundingBox::new(point(-1.0, minimum, -1.0), point(1.0, maximum, 1.0))
+    }
+
+    // Cylindrical mapping: u goes around the cylinder and v is y, so one unit of height
+    // covers the texture and its wrap mode decides what lies above and below.
+    pub fn uv_map(pt: &Tuple) -> (f64, f64) {
+        let theta = pt.x.atan2(pt.z);
+        let raw_u = theta / (2.0 * PI);
+        let u = 1.0 - (raw_u + 0.5);
+        (u, pt.y)
+    }
+}
+
+// Intersects the ray with the end caps at y = minimum and y = maximum.
+// The radius of each cap is given as a function of its y value, so cones can share this.
+pub fn intersect_caps(
+    s: &Shape,
+    ray: &Ray,
+    minimum: f64,
+    maximum: f64,
+    radius: impl Fn(f64) -> f64,
+    xs: &mut Vec<Intersection>,
+) {
+    // Caps only matter if the ray isn't parallel to them.
+    if ray.direction.y.abs() < EPSILON {
+        return;
+    }
+
+    for y in [minimum, maximum] {
+        let t = (y - ray.origin.y) / ray.direction.y;
+        if check_cap(ray, t, radius(y)) {
+            xs.push(intersection(t, s));
+        }
+    }
+}
+
+// Checks whether the intersection at t is within the radius of the cap.
+fn check_cap(ray: &Ray, t: f64, radius: f64) -> bool {
+    let x = ray.origin.x + t * ray.direction.x;
+    let z = ray.origin.z + t * ray.direction.z;
+    x.powi(2) + z.powi(2) <= radius.powi(2)
+}
+
+pub fn cylinder() -> Shape {
+    Shape::new(ShapeType::Cylinder {
+        minimum: f64::NEG_INFINITY,
+        maximum: f64::INFINITY,
+        closed: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::FRAC_1_SQRT_2;
+
+    use crate::{rays::ray, tuple::normalise};
+
+    use super::*;
+
+    fn truncated(minimum: f64, maximum: f64, closed: bool) -> Shape {
+        Shape::new(ShapeType::Cylinder {
+            minimum,
+            maximum,
+            closed,
+        })
+    }
+
+    #[test]
+    fn ray_misses_a_cylinder() {
+        let cyl = cylinder();
+        let examples = [
+            (point(1.0, 0.0, 0.0), vector(0.0, 1.0, 0.0)),
+            (point(0.0, 0.0, 0.0), vector(0.0, 1.0, 0.0)),
+            (point(0.0, 0.0, -5.0), vector(1.0, 1.0, 1.0)),
+        ];
+        for (origin, direction) in examples {
+            let r = ray(origin, normalise(&direction));
+            let xs = cyl.intersect(&r);
+            assert!(xs.is_empty());
+        }
+    }
+
+    #[test]
+    fn ray_strikes_a_cylinder() {
+        let cyl = cylinder();
+        let examples = [
+            (point(1.0, 0.0, -5.0), vector(0.0, 0.0, 1.0), 5.0, 5.0),
+            (point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0), 4.0, 6.0),
+            (
+                point(0.5, 0.0, -5.0),
+                vector(0.1, 1.0, 1.0),
+                6.80798,
+                7.08872,
+            ),
+        ];
+        for (origin, direction, t0, t1) in examples {
+            let r = ray(origin, normalise(&direction));
+            let xs = cyl.intersect(&r);
+            assert_eq!(xs.len(), 2);
+            assert!((xs[0].t - t0).abs() < EPSILON);
+            assert!((xs[1].t - t1).abs() < EPSILON);
+        }
+    }
+
+    #[test]
+    fn normal_on_a_cylinder() {
+        let cyl = cylinder();
+        let examples = [
+            (point(1.0, 0.0, 0.0), vector(1.0, 0.0, 0.0)),
+            (point(0.0, 5.0, -1.0), vector(0.0, 0.0, -1.0)),
+            (point(0.0, -2.0, 1.0), vector(0.0, 0.0, 1.0)),
+            (point(-1.0, 1.0, 0.0), vector(-1.0, 0.0, 0.0)),
+        ];
+        for (p, expected) in examples {
+            assert_eq!(cyl.normal_at(&p), expected);
+        }
+    }
+
+    #[test]
+    fn default_cylinder_is_infinite_and_open() {
+        let cyl = cylinder();
+        assert_eq!(
+            cyl.shape_type,
+            ShapeType::Cylinder {
+                minimum: f64::NEG_INFINITY,
+                maximum: f64::INFINITY,
+                closed: false,
+            }
+        );
+    }
+
+    #[test]
+    fn bounding_box_of_cylinders() {
+        let b = cylinder().bounds();
+        assert_eq!(b.min.y, f64::NEG_INFINITY);
+        assert_eq!(b.max.y, f64::INFINITY);
+
+        let b = truncated(-5.0, 3.0, true).bounds();
+        assert_eq!(b.min, point(-1.0, -5.0, -1.0));
+        assert_eq!(b.max, point(1.0, 3.0, 1.0));
+    }
+
+    #[test]
+    fn intersecting_a_constrained_cylinder() {
+        let cyl = truncated(1.0, 2.0, false);
+        let examples = [
+            (point(0.0, 1.5, 0.0), vector(0.1, 1.0, 0.0), 0),
+            (point(0.0, 3.0, -5.0), vector(0.0, 0.0, 1.0), 0),
+            (point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0), 0),
+            (point(0.0, 2.0, -5.0), vector(0.0, 0.0, 1.0), 0),
+            (point(0.0, 1.0, -5.0), vector(0.0, 0.0, 1.0), 0),
+            (point(0.0, 1.5, -2.0), vector(0.0, 0.0, 1.0), 2),
+        ];
+        for (origin, direction, count) in examples {
+            let r = ray(origin, normalise(&direction));
+            let xs = cyl.intersect(&r);
+            assert_eq!(xs.len(), count);
+        }
+    }
+
+    #[test]
+    fn intersecting_the_caps_of_a_closed_cylinder() {
+        let cyl = truncated(1.0, 2.0, true);
+        let examples = [
+            (point(0.0, 3.0, 0.0), vector(0.0, -1.0, 0.0), 2),
+            (point(0.0, 3.0, -2.0), vector(0.0, -1.0, 2.0), 2),
+            // Corner case
+            (point(0.0, 4.0, -2.0), vector(0.0, -1.0, 1.0), 2),
+            (point(0.0, 0.0, -2.0), vector(0.0, 1.0, 2.0), 2),
+            // Corner case
+            (point(0.0, -1.0, -2.0), vector(0.0, 1.0, 1.0), 2),
+        ];
+        for (origin, direction, count) in examples {
+            let r = ray(origin, normalise(&direction));
+            let xs = cyl.intersect(&r);
+            assert_eq!(xs.len(), count);
+        }
+    }
+
+    #[test]
+    fn normal_on_the_end_caps_of_a_cylinder() {
+        let cyl = truncated(1.0, 2.0, true);
+        let examples = [
+            (point(0.0, 1.0, 0.0), vector(0.0, -1.0, 0.0)),
+            (point(0.5, 1.0, 0.0), vector(0.0, -1.0, 0.0)),
+            (point(0.0, 1.0, 0.5), vector(0.0, -1.0, 0.0)),
+            (point(0.0, 2.0, 0.0), vector(0.0, 1.0, 0.0)),
+            (point(0.5, 2.0, 0.0), vector(0.0, 1.0, 0.0)),
+            (point(0.0, 2.0, 0.5), vector(0.0, 1.0, 0.0)),
+        ];
+        for (p, expected) in examples {
+            assert_eq!(cyl.normal_at(&p), expected);
+        }
+    }
+
+    #[test]
+    fn using_a_cylindrical_mapping_on_a_3d_point() {
+        let h = FRAC_1_SQRT_2;
+        let examples = [
+            (point(0.0, 0.0, -1.0), 0.0, 0.0),
+            (point(0.0, 0.5, -1.0), 0.0, 0.5),
+            (point(0.0, 1.0, -1.0), 0.0, 1.0),
+            (point(h, 0.5, -h), 0.125, 0.5),
+            (point(1.0, 0.5, 0.0), 0.25, 0.5),
+            (point(h, 0.5, h), 0.375, 0.5),
+            (point(0.0, -0.25, 1.0), 0.5, -0.25),
+            (point(-h, 0.5, h), 0.625, 0.5),
+            (point(-1.0, 1.25, 0.0), 0.75, 1.25),
+            (point(-h, 0.5, -h), 0.875, 0.5),
+        ];
+        for (p, u, v) in examples {
+            let (actual_u, actual_v) = Cylinder::uv_map(&p);
+            assert!((actual_u - u).abs() < EPSILON);
+            assert!((actual_v - v).abs() < EPSILON);
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use crate::{
+    bounds::BoundingBox,
+    bvh,
+    intersections::Intersection,
+    rays::Ray,
+    shape::{Shape, ShapeType},
+};
+
+pub struct Group {}
+
+impl Group {
+    pub fn local_intersect(s: &Shape, ray: &Ray) -> Vec<Intersection> {
+        let ShapeType::Group { children, bvh } = &s.shape_type else {
+            unreachable!("not a group")
+        };
+
+        // The ray is already in the group's space, each child then applies its own transform.
+        let mut xs = match bvh {
+            Some(bvh) => bvh::intersect(bvh, children, ray),
+            None => children.iter().flat_map(|c| c.intersect(ray)).collect(),
+        };
+        xs.sort_by(|a, b| a.t.total_cmp(&b.t));
+        xs
+    }
+
+    pub fn bounds(s: &Shape) -> BoundingBox {
+        let mut result = BoundingBox::empty();
+        for child in s.children() {
+            result.merge(&child.parent_space_bounds());
+        }
+        result
+    }
+}
+
+pub fn group() -> Shape {
+    Shape::new(ShapeType::Group {
+        children: vec![],
+        bvh: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use crate::{
+        matrix::Matrix,
+        rays::ray,
+        sphere::sphere,
+        transformations::{rotation_y, scaling, translation},
+        tuple::{point, vector},
+    };
+
+    use super::*;
+
+    #[test]
+    fn creating_a_new_group() {
+        let g = group();
+        assert_eq!(g.transform(), &Matrix::identity_4x4());
+        assert!(g.children().is_empty());
+    }
+
+    #[test]
+    fn shape_has_no_parents_by_default() {
+        let s = sphere();
+        assert!(s.parents().is_empty());
+    }
+
+    #[test]
+    fn adding_a_child_to_a_group() {
+        let mut g = group();
+        g.set_transform(translation(1.0, 2.0, 3.0));
+        let s = sphere();
+        g.add_child(s.clone());
+        assert_eq!(g.children(), &[s]);
+        assert_eq!(g.children()[0].parents(), &[translation(1.0, 2.0, 3.0)]);
+    }
+
+    #[test]
+    fn changing_group_transform_updates_children() {
+        let mut g = group();
+        g.add_child(sphere());
+        g.set_transform(scaling(2.0, 2.0, 2.0));
+        assert_eq!(g.children()[0].parents(), &[scaling(2.0, 2.0, 2.0)]);
+    }
+
+    #[test]
+    fn intersecting_ray_with_empty_group() {
+        let g = group();
+        let r = ray(point(0.0, 0.0, 0.0), vector(0.0, 0.0, 1.0));
+        assert!(g.intersect(&r).is_empty());
+    }
+
+    #[test]
+    fn intersecting_ray_with_nonempty_group() {
+        let mut g = group();
+        let s1 = sphere();
+        let mut s2 = sphere();
+        s2.set_transform(translation(0.0, 0.0, -3.0));
+        let mut s3 = sphere();
+        s3.set_transform(translation(5.0, 0.0, 0.0));
+        g.add_child(s1.clone());
+        g.add_child(s2.clone());
+        g.add_child(s3);
+        let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let xs = g.intersect(&r);
+        assert_eq!(xs.len(), 4);
+        assert_eq!(xs[0].object, s2);
+        assert_eq!(xs[1].object, s2);
+        assert_eq!(xs[2].object, s1);
+        assert_eq!(xs[3].object, s1);
+    }
+
+    #[test]
+    fn intersecting_transformed_group() {
+        let mut g = group();
+        g.set_transform(scaling(2.0, 2.0, 2.0));
+        let mut s = sphere();
+        s.set_transform(translation(5.0, 0.0, 0.0));
+        g.add_child(s);
+        let r = ray(point(10.0, 0.0, -10.0), vector(0.0, 0.0, 1.0));
+        let xs = g.intersect(&r);
+        assert_eq!(xs.len(), 2);
+    }
+
+    #[test]
+    fn group_has_a_bounding_box_that_contains_its_children() {
+        let mut s = sphere();
+        s.set_transform(translation(2.0, 5.0, -3.0) * scaling(2.0, 2.0, 2.0));
+        let mut c = Shape::new(ShapeType::Cylinder {
+            minimum: -2.0,
+            maximum: 2.0,
+            closed: false,
+        });
+        c.set_transform(translation(-4.0, -1.0, 4.0) * scaling(0.5, 1.0, 0.5));
+        let mut shape = group();
+        shape.add_child(s);
+        shape.add_child(c);
+        let b = shape.bounds();
+        assert_eq!(b.min, point(-4.5, -3.0, -5.0));
+        assert_eq!(b.max, point(4.0, 7.0, 4.5));
+    }
+
+    #[test]
+    fn empty_child_group_does_not_make_bounds_infinite() {
+        let mut empty = group();
+        empty.set_transform(translation(1.0, 2.0, 3.0));
+        let mut shape = group();
+        shape.add_child(sphere());
+        shape.add_child(empty);
+        let b = shape.bounds();
+        assert_eq!(b.min, point(-1.0, -1.0, -1.0));
+        assert_eq!(b.max, point(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn intersecting_group_with_bvh_matches_brute_force() {
+        let mut g = group();
+        for i in 0..10 {
+            let mut s = sphere();
+            s.set_transform(translation(i as f64 * 3.0, 0.0, 0.0));
+            g.add_child(s);
+        }
+        let brute_force = g.clone();
+        g.build_bvh();
+        let r = ray(point(12.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let xs = g.intersect(&r);
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs, brute_force.intersect(&r));
+        assert_eq!(xs[0].object, g.children()[4]);
+    }
+
+    #[test]
+    fn adding_a_child_discards_the_bvh() {
+        let mut g = group();
+        g.add_child(sphere());
+        g.build_bvh();
+        let mut s = sphere();
+        s.set_transform(translation(5.0, 0.0, 0.0));
+        g.add_child(s);
+        let r = ray(point(5.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        assert_eq!(g.intersect(&r).len(), 2);
+    }
+
+    fn nested_groups(inner_scaling: Matrix) -> Shape {
+        let mut g1 = group();
+        g1.set_transform(rotation_y(PI / 2.0));
+        let mut g2 = group();
+        g2.set_transform(inner_scaling);
+        let mut s = sphere();
+        s.set_transform(translation(5.0, 0.0, 0.0));
+        g2.add_child(s);
+        g1.add_child(g2);
+        g1
+    }
+
+    #[test]
+    fn converting_point_from_world_to_object_space() {
+        let g1 = nested_groups(scaling(2.0, 2.0, 2.0));
+        let s = &g1.children()[0].children()[0];
+        let p = s.world_to_object(&point(-2.0, 0.0, -10.0));
+        assert_eq!(p, point(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn converting_normal_from_object_to_world_space() {
+        let g1 = nested_groups(scaling(1.0, 2.0, 3.0));
+        let s = &g1.children()[0].children()[0];
+        let v = 3.0_f64.sqrt() / 3.0;
+        let n = s.normal_to_world(&vector(v, v, v));
+        assert_eq!(n, vector(0.28571, 0.42857, -0.85714));
+    }
+
+    #[test]
+    fn finding_normal_on_child_object() {
+        let g1 = nested_groups(scaling(1.0, 2.0, 3.0));
+        let s = &g1.children()[0].children()[0];
+        let n = s.normal_at(&point(1.7321, 1.1547, -5.5774));
+        // The point is only given to 4 decimal places, so the normal is only that accurate too.
+        assert!((n.x - 0.2857).abs() < 0.0001);
+        assert!((n.y - 0.4286).abs() < 0.0001);
+        assert!((n.z + 0.8571).abs() < 0.0001);
+    }
+}
@@ -0,0 +1,546 @@
+// High dynamic range image files, which keep colors brighter than 1.0 instead of clamping
+// them: Portable Float Map (.pfm) and Radiance RGBE (.hdr).
+
+use std::{
+    fmt,
+    io::{self, Write},
+};
+
+use crate::{canvas::Canvas, color::Color};
+
+#[derive(Debug, PartialEq)]
+pub enum HdrError {
+    // Doesn't start with PF, Pf or #?RADIANCE.
+    UnknownFormat(String),
+    // The header isn't what it should be.
+    Malformed(String),
+    // The file ends part way through a row of pixels.
+    Truncated { row: usize },
+}
+
+impl fmt::Display for HdrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HdrError::UnknownFormat(magic) => write!(f, "unknown HDR format {:?}", magic),
+            HdrError::Malformed(message) => write!(f, "malformed HDR image: {}", message),
+            HdrError::Truncated { row } => {
+                write!(f, "HDR image is truncated: it ends in row {}", row)
+            }
+        }
+    }
+}
+
+impl std::error::Error for HdrError {}
+
+// Splits off the header line at the start of the data, without its newline.
+fn next_line<'a>(data: &mut &'a [u8]) -> Option<&'a str> {
+    let end = data.iter().position(|&c| c == b'\n')?;
+    let line = std::str::from_utf8(&data[..end]).ok();
+    *data = &data[end + 1..];
+    line
+}
+
+fn parse<T: std::str::FromStr>(token: Option<&str>, what: &str) -> Result<T, HdrError> {
+    token
+        .and_then(|t| t.parse().ok())
+        .ok_or_else(|| HdrError::Malformed(format!("missing or invalid {}", what)))
+}
+
+// PFM stores a 32-bit float for each channel. Rows run from the bottom of the image to the
+// top, and a negative scale in the header means the floats are little endian.
+pub fn write_pfm<W: Write>(canvas: &Canvas, mut writer: W) -> io::Result<()> {
+    write!(writer, "PF\n{} {}\n-1.0\n", canvas.width(), canvas.height())?;
+    let mut row = Vec::with_capacity(canvas.width() * 12);
+    for y in (0..canvas.height()).rev() {
+        row.clear();
+        for x in 0..canvas.width() {
+            let pixel = canvas.pixel_at(x, y);
+            for value in [pixel.red, pixel.green, pixel.blue] {
+                row.extend((value as f32).to_le_bytes());
+            }
+        }
+        writer.write_all(&row)?;
+    }
+    writer.flush()
+}
+
+// Reads color (PF) and greyscale (Pf) float maps.
+pub fn read_pfm(data: &[u8]) -> Result<Canvas, HdrError> {
+    let mut rest = data;
+    let channels = match next_line(&mut rest).map(str::trim) {
+        Some("PF") => 3,
+        Some("Pf") => 1,
+        magic => {
+            return Err(HdrError::UnknownFormat(
+                magic.unwrap_or_default().to_string(),
+            ))
+        }
+    };
+
+    let size = next_line(&mut rest).unwrap_or_default();
+    let mut size = size.split_whitespace();
+    let width: usize = parse(size.next(), "width")?;
+    let height: usize = parse(size.next(), "height")?;
+    let scale: f32 = parse(next_line(&mut rest).map(str::trim), "scale")?;
+    if scale == 0.0 {
+        return Err(HdrError::Malformed("scale must not be zero".to_string()));
+    }
+
+    let read_f32 = |bytes: &[u8]| {
+        let bytes = bytes.try_into().unwrap();
+        if scale < 0.0 {
+            f32::from_le_bytes(bytes)
+        } else {
+            f32::from_be_bytes(bytes)
+        }
+    };
+
+    let too_large = || HdrError::Malformed(format!("{}x{} image is too large", width, height));
+    let row_size = width.checked_mul(channels * 4).ok_or_else(too_large)?;
+    let total = row_size.checked_mul(height).ok_or_else(too_large)?;
+    if total > rest.len() {
+        // Rows are stored bottom up, so the first one missing is this far from the bottom.
+        return Err(HdrError::Truncated {
+            row: height - 1 - rest.len() / row_size,
+        });
+    }
+    let mut canvas = Canvas::new(width, height);
+    // Nothing to read, however many rows the header claims.
+    if width == 0 {
+        return Ok(canvas);
+    }
+    for (i, y) in (0..height).rev().enumerate() {
+        let row = rest
+            .get(i * row_size..(i + 1) * row_size)
+            .ok_or(HdrError::Truncated { row: y })?;
+        for (x, pixel) in row.chunks_exact(channels * 4).enumerate() {
+            let values: Vec<f64> = pixel.chunks_exact(4).map(|v| read_f32(v) as f64).collect();
+            let color = match values[..] {
+                [grey] => Color::new(grey, grey, grey),
+                [red, green, blue] => Color::new(red, green, blue),
+                _ => unreachable!("PFM pixels have one or three channels"),
+            };
+            canvas.write_pixel(x, y, color);
+        }
+    }
+    Ok(canvas)
+}
+
+// RGBE shares one exponent between the three channels, so each pixel takes four bytes.
+// Negative values can't be stored and become 0.0.
+fn to_rgbe(color: &Color) -> [u8; 4] {
+    let (red, green, blue) = (
+        color.red.max(0.0),
+        color.green.max(0.0),
+        color.blue.max(0.0),
+    );
+    let brightest = red.max(green).max(blue);
+    if brightest < 1e-32 {
+        return [0; 4];
+    }
+    // brightest = mantissa * 2^exponent, with the mantissa between 0.5 and 1.0.
+    let mut exponent = brightest.log2().floor() as i32 + 1;
+    if brightest / 2f64.powi(exponent) >= 1.0 {
+        exponent += 1;
+    }
+    let scale = 256.0 / 2f64.powi(exponent);
+    [
+        (red * scale).min(255.0) as u8,
+        (green * scale).min(255.0) as u8,
+        (blue * scale).min(255.0) as u8,
+        (exponent + 128).clamp(0, 255) as u8,
+    ]
+}
+
+fn from_rgbe(rgbe: &[u8]) -> Color {
+    if rgbe[3] == 0 {
+        return Color::new(0.0, 0.0, 0.0);
+    }
+    // Values were rounded down when written, so use the middle of the range they came from.
+    let scale = 2f64.powi(rgbe[3] as i32 - (128 + 8));
+    Color::new(
+        (rgbe[0] as f64 + 0.5) * scale,
+        (rgbe[1] as f64 + 0.5) * scale,
+        (rgbe[2] as f64 + 0.5) * scale,
+    )
+}
+
+// Rows this wide or more can't be run length encoded.
+const MAX_RLE_WIDTH: usize = 0x8000;
+// Nor can rows narrower than this.
+const MIN_RLE_WIDTH: usize = 8;
+
+// Run length encodes one channel of a row: a byte over 128 is followed by a single value to
+// repeat that many times less 128, and any other count by that many values to copy.
+fn write_rle_channel(out: &mut Vec<u8>, values: &[u8]) {
+    let mut i = 0;
+    while i < values.len() {
+        let run = values[i..]
+            .iter()
+            .take(127)
+            .take_while(|&&v| v == values[i])
+            .count();
+        if run > 2 {
+            out.extend([128 + run as u8, values[i]]);
+            i += run;
+            continue;
+        }
+
+        // Copy values until the next run worth encoding.
+        let start = i;
+        while i < values.len() && i - start < 128 {
+            if i + 2 < values.len() && values[i] == values[i + 1] && values[i] == values[i + 2] {
+                break;
+            }
+            i += 1;
+        }
+        out.push((i - start) as u8);
+        out.extend(&values[start..i]);
+    }
+}
+
+// Writes a Radiance .hdr file, run length encoding the rows when they're a size that allows it.
+pub fn write_rgbe<W: Write>(canvas: &Canvas, mut writer: W) -> io::Result<()> {
+    let (width, height) = (canvas.width(), canvas.height());
+    write!(
+        writer,
+        "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+        height, width
+    )?;
+
+    let rle = (MIN_RLE_WIDTH..MAX_RLE_WIDTH).contains(&width);
+    let mut row = Vec::with_capacity(width * 4);
+    let mut out = Vec::with_capacity(width * 4 + 4);
+    for y in 0..height {
+        row.clear();
+        for x in 0..width {
+            row.extend(to_rgbe(canvas.pixel_at(x, y)));
+        }
+
+        if rle {
+            out.clear();
+            out.extend([2, 2, (width >> 8) as u8, width as u8]);
+            for channel in 0..4 {
+                let values: Vec<u8> = row.iter().skip(channel).step_by(4).copied().collect();
+                write_rle_channel(&mut out, &values);
+            }
+            writer.write_all(&out)?;
+        } else {
+            writer.write_all(&row)?;
+        }
+    }
+    writer.flush()
+}
+
+// Reads a Radiance .hdr file, either flat or run length encoded. Only the usual orientation,
+// with rows from top to bottom and pixels from left to right, is supported.
+pub fn read_rgbe(data: &[u8]) -> Result<Canvas, HdrError> {
+    let mut rest = data;
+    let magic = next_line(&mut rest).unwrap_or_default();
+    if !magic.starts_with("#?") {
+        return Err(HdrError::UnknownFormat(magic.to_string()));
+    }
+
+    // Settings, one per line, up to a blank line.
+    loop {
+        let Some(line) = next_line(&mut rest) else {
+            return Err(HdrError::Malformed("missing end of header".to_string()));
+        };
+        if line.trim().is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix("FORMAT=") {
+            if format.trim() != "32-bit_rle_rgbe" {
+                return Err(HdrError::Malformed(format!(
+                    "unsupported format {}",
+                    format
+                )));
+            }
+        }
+    }
+
+    let size = next_line(&mut rest).unwrap_or_default();
+    let tokens: Vec<&str> = size.split_whitespace().collect();
+    let (height, width): (usize, usize) = match tokens[..] {
+        ["-Y", height, "+X", width] => {
+            (parse(Some(height), "height")?, parse(Some(width), "width")?)
+        }
+        _ => {
+            return Err(HdrError::Malformed(format!(
+                "unsupported resolution {:?}",
+                size
+            )))
+        }
+    };
+
+    // Check the header against what the rest of the file could possibly hold before
+    // allocating anything: four bytes a pixel when flat, and when run length encoded a
+    // four byte marker plus at least one two byte run per 127 pixels of each channel.
+    let too_large = || HdrError::Malformed(format!("{}x{} image is too large", width, height));
+    width.checked_mul(height).ok_or_else(too_large)?;
+    let flat_row = width.checked_mul(4).ok_or_else(too_large)?;
+    let smallest_row = if (MIN_RLE_WIDTH..MAX_RLE_WIDTH).contains(&width) {
+        4 + 8 * width.div_ceil(127)
+    } else {
+        flat_row
+    };
+    if smallest_row.checked_mul(height).ok_or_else(too_large)? > rest.len() {
+        return Err(HdrError::Truncated {
+            row: rest.len() / smallest_row,
+        });
+    }
+
+    let mut canvas = Canvas::new(width, height);
+    // Nothing to read, however many rows the header claims.
+    if width == 0 {
+        return Ok(canvas);
+    }
+    let mut row = vec![0; flat_row];
+    for y in 0..height {
+        let rle = (MIN_RLE_WIDTH..MAX_RLE_WIDTH).contains(&width)
+            && rest.len() >= 4
+            && rest[0] == 2
+            && rest[1] == 2
+            && rest[2] & 0x80 == 0;
+
+        if rle {
+            if ((rest[2] as usize) << 8 | rest[3] as usize) != width {
+                return Err(HdrError::Malformed(format!("row {} is the wrong width", y)));
+            }
+            rest = &rest[4..];
+            for channel in 0..4 {
+                let mut x = 0;
+                while x < width {
+                    let (&count, tail) =
+                        rest.split_first().ok_or(HdrError::Truncated { row: y })?;
+                    let (run, count) = if count > 128 {
+                        (true, count as usize - 128)
+                    } else {
+                        (false, count as usize)
+                    };
+                    if count == 0 || x + count > width {
+                        return Err(HdrError::Malformed(format!("bad run in row {}", y)));
+                    }
+                    let used = if run { 1 } else { count };
+                    let values = tail.get(..used).ok_or(HdrError::Truncated { row: y })?;
+                    for i in 0..count {
+                        row[(x + i) * 4 + channel] = values[if run { 0 } else { i }];
+                    }
+                    x += count;
+                    rest = &tail[used..];
+                }
+            }
+        } else {
+            let flat = rest
+                .get(..width * 4)
+                .ok_or(HdrError::Truncated { row: y })?;
+            row.copy_from_slice(flat);
+            rest = &rest[width * 4..];
+        }
+
+        for (x, rgbe) in row.chunks_exact(4).enumerate() {
+            canvas.write_pixel(x, y, from_rgbe(rgbe));
+        }
+    }
+    Ok(canvas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bright_canvas(width: usize, height: usize) -> Canvas {
+        let mut c = Canvas::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                // Mostly flat with some variation, so RGBE rows have runs and copies.
+                let v = if x % 5 == 0 { x as f64 * 3.7 } else { 0.25 };
+                c.write_pixel(x, y, Color::new(v, 12.5 + y as f64, 0.001 * x as f64));
+            }
+        }
+        c
+    }
+
+    fn assert_close(a: &Canvas, b: &Canvas, tolerance: f64) {
+        assert_eq!((a.width(), a.height()), (b.width(), b.height()));
+        for y in 0..a.height() {
+            for x in 0..a.width() {
+                let (p, q) = (a.pixel_at(x, y), b.pixel_at(x, y));
+                for (u, v) in [(p.red, q.red), (p.green, q.green), (p.blue, q.blue)] {
+                    assert!(
+                        (u - v).abs() <= tolerance * u.abs().max(v.abs()).max(1e-6),
+                        "({}, {}): {} != {}",
+                        x,
+                        y,
+                        u,
+                        v
+                    );
+                }
+            }
+        }
+    }
+
+    // RGBE keeps about one part in 256 of the brightest channel, so compare against that.
+    fn assert_rgbe_close(a: &Canvas, b: &Canvas) {
+        assert_eq!((a.width(), a.height()), (b.width(), b.height()));
+        for y in 0..a.height() {
+            for x in 0..a.width() {
+                let (p, q) = (a.pixel_at(x, y), b.pixel_at(x, y));
+                let brightest = p.red.max(p.green).max(p.blue);
+                for (u, v) in [(p.red, q.red), (p.green, q.green), (p.blue, q.blue)] {
+                    assert!((u - v).abs() <= brightest / 128.0, "({}, {})", x, y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn writing_a_pfm() {
+        let mut c = Canvas::new(2, 2);
+        c.write_pixel(0, 1, Color::new(1.5, -2.0, 100.0));
+        let mut pfm = vec![];
+        write_pfm(&c, &mut pfm).unwrap();
+        let header = b"PF\n2 2\n-1.0\n";
+        assert_eq!(&pfm[..header.len()], header);
+        assert_eq!(pfm.len(), header.len() + 2 * 2 * 3 * 4);
+        // The bottom row comes first.
+        assert_eq!(&pfm[header.len()..header.len() + 4], &1.5f32.to_le_bytes());
+    }
+
+    #[test]
+    fn pfm_round_trip_keeps_values_outside_zero_to_one() {
+        let c = bright_canvas(7, 3);
+        let mut pfm = vec![];
+        write_pfm(&c, &mut pfm).unwrap();
+        assert_close(&read_pfm(&pfm).unwrap(), &c, 1e-6);
+    }
+
+    #[test]
+    fn reading_a_big_endian_greyscale_pfm() {
+        let mut pfm = b"Pf\n2 1\n1.0\n".to_vec();
+        pfm.extend(0.5f32.to_be_bytes());
+        pfm.extend(4.0f32.to_be_bytes());
+        let c = read_pfm(&pfm).unwrap();
+        assert_eq!(*c.pixel_at(0, 0), Color::new(0.5, 0.5, 0.5));
+        assert_eq!(*c.pixel_at(1, 0), Color::new(4.0, 4.0, 4.0));
+    }
+
+    #[test]
+    fn reading_bad_pfms() {
+        assert_eq!(
+            read_pfm(b"P6\n1 1\n255\n"),
+            Err(HdrError::UnknownFormat("P6".to_string()))
+        );
+        assert!(matches!(
+            read_pfm(b"PF\n1 x\n-1.0\n"),
+            Err(HdrError::Malformed(_))
+        ));
+        assert!(matches!(
+            read_pfm(b"PF\n1 1\n0\n"),
+            Err(HdrError::Malformed(_))
+        ));
+        assert_eq!(
+            read_pfm(b"PF\n1 2\n-1.0\n\0\0\0\0\0\0\0\0\0\0\0\0"),
+            Err(HdrError::Truncated { row: 0 })
+        );
+    }
+
+    #[test]
+    fn reading_pfms_claiming_huge_sizes() {
+        assert_eq!(
+            read_pfm(b"PF\n100000 100000\n-1.0\n\0\0\0\0"),
+            Err(HdrError::Truncated { row: 99999 })
+        );
+        let pfm = format!("PF\n{} 2\n-1.0\n", usize::MAX / 4);
+        assert!(matches!(
+            read_pfm(pfm.as_bytes()),
+            Err(HdrError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn rgbe_encoding() {
+        assert_eq!(to_rgbe(&Color::new(0.0, 0.0, 0.0)), [0, 0, 0, 0]);
+        assert_eq!(to_rgbe(&Color::new(1.0, 0.5, 0.0)), [128, 64, 0, 129]);
+        assert_eq!(to_rgbe(&Color::new(-1.0, 0.5, 0.0)), [0, 128, 0, 128]);
+        assert_eq!(
+            from_rgbe(&[128, 64, 0, 129]),
+            Color::new(1.00390625, 0.50390625, 0.00390625)
+        );
+    }
+
+    #[test]
+    fn rgbe_round_trip_with_run_length_encoding() {
+        let c = bright_canvas(300, 4);
+        let mut hdr = vec![];
+        write_rgbe(&c, &mut hdr).unwrap();
+        // Smaller than four bytes a pixel.
+        assert!(hdr.len() < 300 * 4 * 4);
+        assert_rgbe_close(&read_rgbe(&hdr).unwrap(), &c);
+    }
+
+    #[test]
+    fn run_length_encoding_shrinks_flat_rows() {
+        let mut c = Canvas::new(500, 1);
+        for x in 0..500 {
+            c.write_pixel(x, 0, Color::new(3.0, 2.0, 1.0));
+        }
+        let mut hdr = vec![];
+        write_rgbe(&c, &mut hdr).unwrap();
+        assert!(hdr.len() < 100);
+        assert_rgbe_close(&read_rgbe(&hdr).unwrap(), &c);
+    }
+
+    #[test]
+    fn rgbe_round_trip_for_narrow_images() {
+        // Too narrow to run length encode, so written flat.
+        let mut c = Canvas::new(3, 2);
+        c.write_pixel(0, 0, Color::new(1.0, 2.0, 4.0));
+        c.write_pixel(2, 1, Color::new(1000.0, 0.0, 0.5));
+        let mut hdr = vec![];
+        write_rgbe(&c, &mut hdr).unwrap();
+        let header = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 2 +X 3\n";
+        assert_eq!(&hdr[..header.len()], header);
+        assert_eq!(hdr.len(), header.len() + 3 * 2 * 4);
+        assert_rgbe_close(&read_rgbe(&hdr).unwrap(), &c);
+    }
+
+    #[test]
+    fn reading_bad_rgbes() {
+        assert!(matches!(
+            read_rgbe(b"P3\n"),
+            Err(HdrError::UnknownFormat(_))
+        ));
+        assert!(matches!(
+            read_rgbe(b"#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n-Y 1 +X 1\n\0\0\0\0"),
+            Err(HdrError::Malformed(_))
+        ));
+        assert!(matches!(
+            read_rgbe(b"#?RADIANCE\n\n+Y 1 +X 1\n\0\0\0\0"),
+            Err(HdrError::Malformed(_))
+        ));
+        assert_eq!(
+            read_rgbe(b"#?RADIANCE\n\n-Y 2 +X 1\n\0\0\0\0"),
+            Err(HdrError::Truncated { row: 1 })
+        );
+    }
+
+    #[test]
+    fn reading_rgbes_claiming_huge_sizes() {
+        let hdr = b"#?\n\n-Y 100000 +X 100000\n";
+        assert_eq!(
+            read_rgbe(&[&hdr[..], &[0; 20][..]].concat()),
+            Err(HdrError::Truncated { row: 0 })
+        );
+        // Run length encoded rows can't be smaller than one run per 127 pixels per channel.
+        assert_eq!(
+            read_rgbe(b"#?\n\n-Y 100000 +X 1000\n\x02\x02\x03\xe8"),
+            Err(HdrError::Truncated { row: 0 })
+        );
+        let hdr = format!("#?\n\n-Y 2 +X {}\n", usize::MAX / 2);
+        assert!(matches!(
+            read_rgbe(hdr.as_bytes()),
+            Err(HdrError::Malformed(_))
+        ));
+    }
+}
@@ -2,23 +2,32 @@ use crate::{
     matrix::EPSILON,
     rays::{position, Ray},
     shape::Shape,
-    tuple::{dot, Tuple},
+    tuple::{dot, reflect, Tuple},
 };
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Intersection {
     pub t: f64,
     pub object: Shape,
+    // Where on the surface the hit was, only meaningful for triangles.
+    pub u: f64,
+    pub v: f64,
 }
 
 pub fn intersection(t: f64, object: &Shape) -> Intersection {
+    intersection_with_uv(t, object, 0.0, 0.0)
+}
+
+pub fn intersection_with_uv(t: f64, object: &Shape, u: f64, v: f64) -> Intersection {
     Intersection {
         t,
         object: object.clone(),
+        u,
+        v,
     }
 }
 
-fn intersections(is: Vec<Intersection>) -> Vec<Intersection> {
+pub fn intersections(is: Vec<Intersection>) -> Vec<Intersection> {
     is
 }
 
@@ -44,19 +53,27 @@ pub fn hit(xs: &Vec<Intersection>) -> Option<&Intersection> {
 }
 
 pub struct Computations {
-    t: f64,
+    pub t: f64,
     pub object: Shape,
     pub point: Tuple,
     pub over_point: Tuple,
+    pub under_point: Tuple,
     pub eyev: Tuple,
     pub normalv: Tuple,
+    pub reflectv: Tuple,
     pub inside: bool,
+    pub n1: f64,
+    pub n2: f64,
 }
 
-pub fn prepare_computations(intersection: &Intersection, ray: &Ray) -> Computations {
+pub fn prepare_computations(
+    intersection: &Intersection,
+    ray: &Ray,
+    xs: &[Intersection],
+) -> Computations {
     let point = position(ray, intersection.t);
     let eyev = -ray.direction.clone();
-    let mut normalv = Shape::normal_at(&intersection.object, &point);
+    let mut normalv = intersection.object.normal_at_hit(&point, intersection);
     let inside = if dot(&normalv, &eyev) < 0.0 {
         normalv = -normalv;
         true
@@ -66,24 +83,82 @@ pub fn prepare_computations(intersection: &Intersection, ray: &Ray) -> Computati
 
     let point = position(ray, intersection.t);
     let over_point = &point + &(&normalv * EPSILON);
+    let under_point = &point - &(&normalv * EPSILON);
+    let reflectv = reflect(&ray.direction, &normalv);
+    let (n1, n2) = refractive_indices(intersection, xs);
 
     Computations {
         t: intersection.t,
         object: intersection.object.clone(),
         point,
         over_point,
+        under_point,
         eyev,
         normalv,
+        reflectv,
         inside,
+        n1,
+        n2,
     }
 }
 
+// Walks the sorted intersections keeping track of which objects the ray is
+// currently inside, to find the refractive indices either side of the hit.
+fn refractive_indices(hit: &Intersection, xs: &[Intersection]) -> (f64, f64) {
+    let mut containers: Vec<&Shape> = vec![];
+    let mut n1 = 1.0;
+    let mut n2 = 1.0;
+
+    for i in xs {
+        if i == hit {
+            n1 = containers
+                .last()
+                .map_or(1.0, |o| o.material().refractive_index);
+        }
+
+        match containers.iter().position(|o| **o == i.object) {
+            Some(index) => {
+                containers.remove(index);
+            }
+            None => containers.push(&i.object),
+        }
+
+        if i == hit {
+            n2 = containers
+                .last()
+                .map_or(1.0, |o| o.material().refractive_index);
+            break;
+        }
+    }
+
+    (n1, n2)
+}
+
+// Approximates the Fresnel effect: how much light is reflected rather than refracted.
+pub fn schlick(comps: &Computations) -> f64 {
+    let mut cos = dot(&comps.eyev, &comps.normalv);
+
+    // Total internal reflection can only occur if n1 > n2.
+    if comps.n1 > comps.n2 {
+        let n = comps.n1 / comps.n2;
+        let sin2_t = n * n * (1.0 - cos * cos);
+        if sin2_t > 1.0 {
+            return 1.0;
+        }
+        cos = (1.0 - sin2_t).sqrt();
+    }
+
+    let r0 = ((comps.n1 - comps.n2) / (comps.n1 + comps.n2)).powi(2);
+    r0 + (1.0 - r0) * (1.0 - cos).powi(5)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{
+        plane::plane,
         rays::ray,
-        sphere::sphere,
-        transformations::translation,
+        sphere::{glass_sphere, sphere},
+        transformations::{scaling, translation},
         tuple::{point, vector},
     };
 
@@ -145,13 +220,14 @@ mod tests {
         let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
         let s = sphere();
         let i = intersection(4.0, &s);
-        let comps = prepare_computations(&i, &r);
+        let xs = vec![i.clone()];
+        let comps = prepare_computations(&i, &r, &xs);
         assert_eq!(comps.t, i.t);
         assert_eq!(comps.object, i.object);
         assert_eq!(comps.point, point(0.0, 0.0, -1.0));
         assert_eq!(comps.eyev, vector(0.0, 0.0, -1.0));
         assert_eq!(comps.normalv, vector(0.0, 0.0, -1.0));
-        assert_eq!(comps.inside, false);
+        assert!(!comps.inside);
     }
 
     #[test]
@@ -159,13 +235,14 @@ mod tests {
         let r = ray(point(0.0, 0.0, 0.0), vector(0.0, 0.0, 1.0));
         let s = sphere();
         let i = intersection(1.0, &s);
-        let comps = prepare_computations(&i, &r);
+        let xs = vec![i.clone()];
+        let comps = prepare_computations(&i, &r, &xs);
         assert_eq!(comps.t, i.t);
         assert_eq!(comps.object, i.object);
         assert_eq!(comps.point, point(0.0, 0.0, 1.0));
         assert_eq!(comps.eyev, vector(0.0, 0.0, -1.0));
         assert_eq!(comps.normalv, vector(0.0, 0.0, -1.0));
-        assert_eq!(comps.inside, true);
+        assert!(comps.inside);
     }
 
     #[test]
@@ -174,8 +251,102 @@ mod tests {
         let mut s = sphere();
         s.set_transform(translation(0.0, 0.0, 1.0));
         let i = intersection(5.0, &s);
-        let comps = prepare_computations(&i, &r);
+        let xs = vec![i.clone()];
+        let comps = prepare_computations(&i, &r, &xs);
         assert!(comps.over_point.z < -EPSILON / 2.0);
         assert!(comps.point.z > comps.over_point.z);
     }
+
+    #[test]
+    fn precompute_reflection_vector() {
+        let s = plane();
+        let r = ray(
+            point(0.0, 1.0, -1.0),
+            vector(0.0, -2.0_f64.sqrt() / 2.0, 2.0_f64.sqrt() / 2.0),
+        );
+        let i = intersection(2.0_f64.sqrt(), &s);
+        let xs = vec![i.clone()];
+        let comps = prepare_computations(&i, &r, &xs);
+        assert_eq!(
+            comps.reflectv,
+            vector(0.0, 2.0_f64.sqrt() / 2.0, 2.0_f64.sqrt() / 2.0)
+        );
+    }
+
+    #[test]
+    fn finding_n1_and_n2_at_various_intersections() {
+        let mut a = glass_sphere();
+        a.set_transform(scaling(2.0, 2.0, 2.0));
+        a.set_material(a.material().clone().refractive_index(1.5));
+        let mut b = glass_sphere();
+        b.set_transform(translation(0.0, 0.0, -0.25));
+        b.set_material(b.material().clone().refractive_index(2.0));
+        let mut c = glass_sphere();
+        c.set_transform(translation(0.0, 0.0, 0.25));
+        c.set_material(c.material().clone().refractive_index(2.5));
+        let r = ray(point(0.0, 0.0, -4.0), vector(0.0, 0.0, 1.0));
+        let xs = intersections(vec![
+            intersection(2.0, &a),
+            intersection(2.75, &b),
+            intersection(3.25, &c),
+            intersection(4.75, &b),
+            intersection(5.25, &c),
+            intersection(6.0, &a),
+        ]);
+        let expected = [
+            (1.0, 1.5),
+            (1.5, 2.0),
+            (2.0, 2.5),
+            (2.5, 2.5),
+            (2.5, 1.5),
+            (1.5, 1.0),
+        ];
+        for (i, (n1, n2)) in expected.iter().enumerate() {
+            let comps = prepare_computations(&xs[i], &r, &xs);
+            assert_eq!(comps.n1, *n1);
+            assert_eq!(comps.n2, *n2);
+        }
+    }
+
+    #[test]
+    fn under_point_is_offset_below_the_surface() {
+        let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let mut s = glass_sphere();
+        s.set_transform(translation(0.0, 0.0, 1.0));
+        let i = intersection(5.0, &s);
+        let xs = intersections(vec![i.clone()]);
+        let comps = prepare_computations(&i, &r, &xs);
+        assert!(comps.under_point.z > EPSILON / 2.0);
+        assert!(comps.point.z < comps.under_point.z);
+    }
+
+    #[test]
+    fn schlick_under_total_internal_reflection() {
+        let s = glass_sphere();
+        let r = ray(point(0.0, 0.0, 2.0_f64.sqrt() / 2.0), vector(0.0, 1.0, 0.0));
+        let xs = intersections(vec![
+            intersection(-(2.0_f64.sqrt()) / 2.0, &s),
+            intersection(2.0_f64.sqrt() / 2.0, &s),
+        ]);
+        let comps = prepare_computations(&xs[1], &r, &xs);
+        assert_eq!(schlick(&comps), 1.0);
+    }
+
+    #[test]
+    fn schlick_with_perpendicular_viewing_angle() {
+        let s = glass_sphere();
+        let r = ray(point(0.0, 0.0, 0.0), vector(0.0, 1.0, 0.0));
+        let xs = intersections(vec![intersection(-1.0, &s), intersection(1.0, &s)]);
+        let comps = prepare_computations(&xs[1], &r, &xs);
+        assert!((schlick(&comps) - 0.04).abs() < EPSILON);
+    }
+
+    #[test]
+    fn schlick_with_small_angle_and_n2_greater_than_n1() {
+        let s = glass_sphere();
+        let r = ray(point(0.0, 0.99, -2.0), vector(0.0, 0.0, 1.0));
+        let xs = intersections(vec![intersection(1.8589, &s)]);
+        let comps = prepare_computations(&xs[0], &r, &xs);
+        assert!((schlick(&comps) - 0.48873).abs() < EPSILON);
+    }
 }
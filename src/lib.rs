@@ -0,0 +1,28 @@
+pub mod bounds;
+pub mod bvh;
+pub mod camera;
+pub mod canvas;
+pub mod color;
+pub mod cone;
+pub mod csg;
+pub mod cube;
+pub mod cylinder;
+pub mod group;
+pub mod hdr;
+pub mod intersections;
+pub mod light;
+pub mod materials;
+pub mod matrix;
+pub mod noise;
+pub mod obj_file;
+pub mod patterns;
+pub mod plane;
+pub mod png;
+pub mod rays;
+pub mod shape;
+pub mod sphere;
+pub mod tone_map;
+pub mod transformations;
+pub mod triangle;
+pub mod tuple;
+pub mod world;
@@ -1,14 +1,91 @@
 use crate::{
     color::Color,
     materials::Material,
+    noise::hash_to_unit,
     shape::Shape,
-    tuple::{dot, normalise, reflect, Tuple},
+    tuple::{dot, magnitude, normalise, reflect, Tuple},
 };
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Light {
+    Point(PointLight),
+    Area(AreaLight),
+    Spot(SpotLight),
+    Directional(DirectionalLight),
+}
+
+impl Light {
+    pub fn intensity(&self) -> Color {
+        match self {
+            Light::Point(light) => light.intensity,
+            Light::Area(light) => light.intensity,
+            Light::Spot(light) => light.intensity,
+            Light::Directional(light) => light.intensity,
+        }
+    }
+
+    // How much of the light is directed towards the point, ignoring shadows.
+    pub fn falloff(&self, point: &Tuple) -> f64 {
+        match self {
+            Light::Spot(light) => light.falloff(point),
+            _ => 1.0,
+        }
+    }
+
+    // How much the light has weakened by the time it has travelled the distance.
+    pub fn attenuation(&self, distance: f64) -> f64 {
+        match self {
+            Light::Point(light) => light.attenuation.at(distance),
+            _ => 1.0,
+        }
+    }
+
+    // Where the point is lit from, and so what to test for shadows against.
+    // Area lights give one sample per cell.
+    pub fn samples(&self, point: &Tuple) -> Vec<LightSample> {
+        match self {
+            Light::Point(light) => vec![LightSample::towards(point, &light.position)],
+            Light::Area(light) => {
+                let mut result = Vec::with_capacity(light.usteps * light.vsteps);
+                for v in 0..light.vsteps {
+                    for u in 0..light.usteps {
+                        let position = light.point_on_light(u, v, point);
+                        result.push(LightSample::towards(point, &position));
+                    }
+                }
+                result
+            }
+            Light::Spot(light) => vec![LightSample::towards(point, &light.position)],
+            Light::Directional(light) => vec![LightSample {
+                direction: -light.direction.clone(),
+                distance: f64::INFINITY,
+            }],
+        }
+    }
+}
+
+// The direction from a point to somewhere on a light, and how far away that is.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LightSample {
+    pub direction: Tuple,
+    pub distance: f64,
+}
+
+impl LightSample {
+    pub fn towards(point: &Tuple, position: &Tuple) -> LightSample {
+        let v = position - point;
+        LightSample {
+            direction: normalise(&v),
+            distance: magnitude(&v),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct PointLight {
     pub position: Tuple,
     pub intensity: Color,
+    pub attenuation: Attenuation,
 }
 
 impl PointLight {
@@ -16,65 +93,276 @@ impl PointLight {
         PointLight {
             position,
             intensity,
+            attenuation: Attenuation::none(),
+        }
+    }
+
+    pub fn attenuation(mut self, attenuation: Attenuation) -> PointLight {
+        self.attenuation = attenuation;
+        self
+    }
+}
+
+// Light intensity is divided by constant + linear * d + quadratic * d^2 at distance d.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Attenuation {
+    pub constant: f64,
+    pub linear: f64,
+    pub quadratic: f64,
+}
+
+impl Attenuation {
+    pub fn new(constant: f64, linear: f64, quadratic: f64) -> Attenuation {
+        Attenuation {
+            constant,
+            linear,
+            quadratic,
         }
     }
+
+    // Full strength at any distance, as in the book.
+    pub fn none() -> Attenuation {
+        Attenuation::new(1.0, 0.0, 0.0)
+    }
+
+    // How real light spreads out. Intensities need to be much brighter to compensate.
+    pub fn inverse_square() -> Attenuation {
+        Attenuation::new(0.0, 0.0, 1.0)
+    }
+
+    pub fn at(&self, distance: f64) -> f64 {
+        1.0 / (self.constant + self.linear * distance + self.quadratic * distance * distance)
+    }
+}
+
+// A light so far away, like the sun, that its rays are parallel and it is the same
+// brightness everywhere. The direction is the way the light travels.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DirectionalLight {
+    pub direction: Tuple,
+    pub intensity: Color,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Tuple, intensity: Color) -> DirectionalLight {
+        DirectionalLight {
+            direction: normalise(&direction),
+            intensity,
+        }
+    }
+}
+
+// A point light that only shines within a cone around its direction. It is at full
+// strength inside the inner angle, fades out smoothly towards the outer angle, and
+// gives no light beyond it. Angles are measured from the direction, in radians.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpotLight {
+    pub position: Tuple,
+    pub direction: Tuple,
+    pub inner_angle: f64,
+    pub outer_angle: f64,
+    pub intensity: Color,
+}
+
+impl SpotLight {
+    pub fn new(
+        position: Tuple,
+        direction: Tuple,
+        inner_angle: f64,
+        outer_angle: f64,
+        intensity: Color,
+    ) -> SpotLight {
+        SpotLight {
+            position,
+            direction: normalise(&direction),
+            inner_angle,
+            outer_angle,
+            intensity,
+        }
+    }
+
+    pub fn falloff(&self, point: &Tuple) -> f64 {
+        let to_point = normalise(&(point - &self.position));
+        let cos_angle = dot(&to_point, &self.direction);
+        let (cos_inner, cos_outer) = (self.inner_angle.cos(), self.outer_angle.cos());
+        if cos_angle >= cos_inner {
+            return 1.0;
+        }
+        if cos_angle <= cos_outer {
+            return 0.0;
+        }
+
+        // Smoothstep, so the edge of the pool of light has no visible crease.
+        let t = (cos_angle - cos_outer) / (cos_inner - cos_outer);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+// A rectangular light, split into a grid of cells that each act like a point light.
+// Points that can only see some of the cells are partially shadowed, which softens the
+// edges of shadows.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AreaLight {
+    pub corner: Tuple,
+    // The edges of a single cell.
+    pub uvec: Tuple,
+    pub usteps: usize,
+    pub vvec: Tuple,
+    pub vsteps: usize,
+    pub intensity: Color,
+    // The centre of the light.
+    pub position: Tuple,
+    // Whether to sample a random point in each cell rather than its centre.
+    pub jitter: bool,
+}
+
+impl AreaLight {
+    pub fn new(
+        corner: Tuple,
+        full_uvec: Tuple,
+        usteps: usize,
+        full_vvec: Tuple,
+        vsteps: usize,
+        intensity: Color,
+    ) -> AreaLight {
+        assert!(
+            usteps > 0 && vsteps > 0,
+            "an area light needs at least one cell along each edge"
+        );
+        let position = &corner + &(&(&full_uvec * 0.5) + &(&full_vvec * 0.5));
+        AreaLight {
+            corner,
+            uvec: full_uvec / usteps as f64,
+            usteps,
+            vvec: full_vvec / vsteps as f64,
+            vsteps,
+            intensity,
+            position,
+            jitter: false,
+        }
+    }
+
+    pub fn jitter(mut self, jitter: bool) -> AreaLight {
+        self.jitter = jitter;
+        self
+    }
+
+    // A point in cell (u, v) of the light, as seen from point.
+    pub fn point_on_light(&self, u: usize, v: usize, point: &Tuple) -> Tuple {
+        let (ju, jv) = if self.jitter {
+            (jitter_offset(point, u, v, 0), jitter_offset(point, u, v, 1))
+        } else {
+            (0.5, 0.5)
+        };
+        &self.corner + &(&(&self.uvec * (u as f64 + ju)) + &(&self.vvec * (v as f64 + jv)))
+    }
+}
+
+// A pseudo-random number in [0, 1) derived from the point and cell being sampled.
+fn jitter_offset(point: &Tuple, u: usize, v: usize, axis: u64) -> f64 {
+    hash_to_unit(&[
+        point.x.to_bits(),
+        point.y.to_bits(),
+        point.z.to_bits(),
+        u as u64,
+        v as u64,
+        axis,
+    ])
 }
 
 pub fn lighting(
     material: &Material,
     object: &Shape,
-    light: &PointLight,
+    light: &Light,
     point: &Tuple,
     eye_vec: &Tuple,
     normal_vec: &Tuple,
-    in_shadow: bool,
+    // How much of the light reaches the point, from 0.0 (fully shadowed) to 1.0.
+    light_intensity: f64,
 ) -> Color {
-    let color = match &material.pattern {
-        Some(p) => p.at(&object, &point),
-        None => material.color,
-    };
-
-    // Combine surface color with the light's color/intensity.
-    let effective_color = &color * &light.intensity;
+    ambient(material, object, light, point)
+        + direct_lighting(
+            material,
+            object,
+            light,
+            point,
+            eye_vec,
+            normal_vec,
+            light_intensity,
+        )
+}
 
-    // Find the direction to the light source.
-    let light_vec = normalise(&(&light.position - point));
+fn surface_color(material: &Material, object: &Shape, point: &Tuple) -> Color {
+    match &material.pattern {
+        Some(p) => p.at(object, point),
+        None => material.color,
+    }
+}
 
-    // Compute the ambient contribution.
-    let ambient = &effective_color * material.ambient;
+// Light bounced around the scene rather than arriving straight from the light, so it
+// reaches shadowed points too. A point should only get this once, however many lights
+// there are.
+pub fn ambient(material: &Material, object: &Shape, light: &Light, point: &Tuple) -> Color {
+    surface_color(material, object, point) * light.intensity() * material.ambient
+}
 
-    // Represents the cosine of the angle between the light vector and the normal vector.
-    // Negative means the light is on the other side of the surface.
-    let light_dot_normal = dot(&light_vec, normal_vec);
+// The diffuse and specular light arriving straight from the light.
+pub fn direct_lighting(
+    material: &Material,
+    object: &Shape,
+    light: &Light,
+    point: &Tuple,
+    eye_vec: &Tuple,
+    normal_vec: &Tuple,
+    light_intensity: f64,
+) -> Color {
+    // Combine surface color with the light's color/intensity.
+    let effective_color = surface_color(material, object, point) * light.intensity();
 
     let mut diffuse = Color::black();
     let mut specular = Color::black();
 
-    if light_dot_normal >= 0.0 {
-        diffuse = effective_color * material.diffuse * light_dot_normal;
+    // Area lights light the point from each of their samples, which are then averaged.
+    let samples = light.samples(point);
+    for sample in &samples {
+        let light_vec = &sample.direction;
+
+        // Represents the cosine of the angle between the light vector and the normal vector.
+        // Negative means the light is on the other side of the surface.
+        let light_dot_normal = dot(light_vec, normal_vec);
+        if light_dot_normal < 0.0 {
+            continue;
+        }
+
+        let attenuation = light.attenuation(sample.distance);
+        diffuse = diffuse + effective_color * material.diffuse * light_dot_normal * attenuation;
 
         // Represents the cosine of the angle between the reflection vector and the eye vector.
         // Negative means the light reflects away from the eye.
-        let temp = -light_vec;
+        let temp = -light_vec.clone();
         let reflect_vec = reflect(&temp, normal_vec);
         let reflect_dot_eye = dot(&reflect_vec, eye_vec);
 
         if reflect_dot_eye > 0.0 {
             let factor = reflect_dot_eye.powf(material.shininess);
-            specular = &light.intensity * material.specular * factor;
+            specular = specular + light.intensity() * material.specular * factor * attenuation;
         }
     }
 
-    if in_shadow {
-        ambient
-    } else {
-        ambient + diffuse + specular
-    }
+    let falloff = light.falloff(point);
+    (diffuse + specular) * (light_intensity * falloff / samples.len() as f64)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{color::Color, tuple::point};
+    use std::f64::consts::{FRAC_1_SQRT_2, PI};
+
+    use crate::{
+        color::Color,
+        materials::Material,
+        sphere::sphere,
+        tuple::{point, vector},
+    };
 
     use super::*;
 
@@ -82,8 +370,264 @@ mod tests {
     fn point_light_has_position_and_intensity() {
         let intensity = Color::new(1.0, 1.0, 1.0);
         let position = point(0.0, 0.0, 0.0);
-        let light = PointLight::new(position.clone(), intensity.clone());
+        let light = PointLight::new(position.clone(), intensity);
         assert_eq!(light.position, position);
         assert_eq!(light.intensity, intensity);
     }
+
+    fn default_area_light() -> AreaLight {
+        AreaLight::new(
+            point(0.0, 0.0, 0.0),
+            vector(2.0, 0.0, 0.0),
+            4,
+            vector(0.0, 0.0, 1.0),
+            2,
+            Color::white(),
+        )
+    }
+
+    #[test]
+    fn creating_an_area_light() {
+        let light = default_area_light();
+        assert_eq!(light.corner, point(0.0, 0.0, 0.0));
+        assert_eq!(light.uvec, vector(0.5, 0.0, 0.0));
+        assert_eq!(light.usteps, 4);
+        assert_eq!(light.vvec, vector(0.0, 0.0, 0.5));
+        assert_eq!(light.vsteps, 2);
+        assert_eq!(light.position, point(1.0, 0.0, 0.5));
+        assert_eq!(Light::Area(light).samples(&point(0.0, 0.0, 0.0)).len(), 8);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one cell")]
+    fn area_light_with_no_cells_is_rejected() {
+        AreaLight::new(
+            point(0.0, 0.0, 0.0),
+            vector(2.0, 0.0, 0.0),
+            0,
+            vector(0.0, 0.0, 1.0),
+            2,
+            Color::white(),
+        );
+    }
+
+    #[test]
+    fn finding_a_single_point_on_an_area_light() {
+        let light = default_area_light();
+        let examples = [
+            (0, 0, point(0.25, 0.0, 0.25)),
+            (1, 0, point(0.75, 0.0, 0.25)),
+            (0, 1, point(0.25, 0.0, 0.75)),
+            (2, 0, point(1.25, 0.0, 0.25)),
+            (3, 1, point(1.75, 0.0, 0.75)),
+        ];
+        for (u, v, expected) in examples {
+            assert_eq!(light.point_on_light(u, v, &point(0.0, 0.0, 0.0)), expected);
+        }
+    }
+
+    #[test]
+    fn jittered_points_stay_inside_their_cell() {
+        let light = default_area_light().jitter(true);
+        for (u, v) in [(0, 0), (1, 0), (3, 1)] {
+            let p = point(1.0, 2.0, 3.0);
+            let pt = light.point_on_light(u, v, &p);
+            assert!((u as f64 * 0.5..(u + 1) as f64 * 0.5).contains(&pt.x));
+            assert!((v as f64 * 0.5..(v + 1) as f64 * 0.5).contains(&pt.z));
+            // The same point always gets the same samples.
+            assert_eq!(light.point_on_light(u, v, &p), pt);
+        }
+        let centre = light
+            .clone()
+            .jitter(false)
+            .point_on_light(0, 0, &point(0.0, 0.0, 0.0));
+        assert_ne!(light.point_on_light(0, 0, &point(0.0, 0.0, 0.0)), centre);
+    }
+
+    #[test]
+    fn lighting_samples_the_area_light() {
+        let light = Light::Area(AreaLight::new(
+            point(-0.5, -0.5, -5.0),
+            vector(1.0, 0.0, 0.0),
+            2,
+            vector(0.0, 1.0, 0.0),
+            2,
+            Color::white(),
+        ));
+        let shape = sphere();
+        let m = Material::default().ambient(0.1).diffuse(0.9).specular(0.0);
+        let eye = point(0.0, 0.0, -5.0);
+        let examples = [
+            (point(0.0, 0.0, -1.0), Color::new(0.9965, 0.9965, 0.9965)),
+            (
+                point(0.0, FRAC_1_SQRT_2, -FRAC_1_SQRT_2),
+                Color::new(0.62318, 0.62318, 0.62318),
+            ),
+        ];
+        for (pt, expected) in examples {
+            let eye_vec = normalise(&(&eye - &pt));
+            let normal_vec = vector(pt.x, pt.y, pt.z);
+            let result = lighting(&m, &shape, &light, &pt, &eye_vec, &normal_vec, 1.0);
+            assert_eq!(result, expected);
+        }
+    }
+
+    fn default_spot_light() -> SpotLight {
+        // Shining straight down from above the origin.
+        SpotLight::new(
+            point(0.0, 10.0, 0.0),
+            vector(0.0, -1.0, 0.0),
+            PI / 12.0,
+            PI / 6.0,
+            Color::white(),
+        )
+    }
+
+    #[test]
+    fn spot_light_falls_off_between_its_cone_angles() {
+        let light = default_spot_light();
+        let examples = [
+            // Straight down the middle.
+            (point(0.0, 0.0, 0.0), 1.0),
+            // Just inside the inner cone.
+            (point(10.0 * (PI / 12.0).tan() - 0.01, 0.0, 0.0), 1.0),
+            // Just outside the outer cone.
+            (point(0.0, 0.0, 10.0 * (PI / 6.0).tan() + 0.01), 0.0),
+            // Above the light.
+            (point(0.0, 20.0, 0.0), 0.0),
+        ];
+        for (p, expected) in examples {
+            assert_eq!(light.falloff(&p), expected);
+        }
+
+        // Halfway between the cones the falloff is somewhere in between.
+        let angle = (PI / 12.0 + PI / 6.0) / 2.0;
+        let f = light.falloff(&point(10.0 * angle.tan(), 0.0, 0.0));
+        assert!(f > 0.0 && f < 1.0);
+    }
+
+    #[test]
+    fn spot_light_falloff_increases_towards_the_centre() {
+        let light = default_spot_light();
+        let mut previous = 0.0;
+        for i in (0..=20).rev() {
+            let angle = PI / 6.0 * i as f64 / 20.0;
+            let f = light.falloff(&point(10.0 * angle.tan(), 0.0, 0.0));
+            assert!(f >= previous);
+            previous = f;
+        }
+        assert_eq!(previous, 1.0);
+    }
+
+    #[test]
+    fn lighting_outside_the_spot_light_cone_is_only_ambient() {
+        let light = Light::Spot(default_spot_light());
+        let m = Material::default();
+        let eye_vec = vector(0.0, 1.0, 0.0);
+        let normal_vec = vector(0.0, 1.0, 0.0);
+
+        let outside = lighting(
+            &m,
+            &sphere(), // Unused
+            &light,
+            &point(10.0, 0.0, 0.0),
+            &eye_vec,
+            &normal_vec,
+            1.0,
+        );
+        assert_eq!(outside, Color::new(0.1, 0.1, 0.1));
+
+        let inside = lighting(
+            &m,
+            &sphere(), // Unused
+            &light,
+            &point(0.0, 0.0, 0.0),
+            &eye_vec,
+            &normal_vec,
+            1.0,
+        );
+        // Ambient + diffuse + specular
+        assert_eq!(inside, Color::new(1.9, 1.9, 1.9));
+    }
+
+    #[test]
+    fn directional_light_comes_from_the_same_direction_everywhere() {
+        let light = Light::Directional(DirectionalLight::new(
+            vector(0.0, -2.0, 0.0),
+            Color::white(),
+        ));
+        for p in [point(0.0, 0.0, 0.0), point(1e6, -1e6, 3.0)] {
+            let samples = light.samples(&p);
+            assert_eq!(samples.len(), 1);
+            assert_eq!(samples[0].direction, vector(0.0, 1.0, 0.0));
+            assert_eq!(samples[0].distance, f64::INFINITY);
+        }
+    }
+
+    #[test]
+    fn lighting_with_a_directional_light() {
+        let light = Light::Directional(DirectionalLight::new(
+            vector(0.0, -1.0, 1.0),
+            Color::white(),
+        ));
+        let m = Material::default();
+        let eye_vec = vector(0.0, 0.0, -1.0);
+        let normal_vec = vector(0.0, 0.0, -1.0);
+        let result = lighting(
+            &m,
+            &sphere(), // Unused
+            &light,
+            &point(0.0, 0.0, 0.0),
+            &eye_vec,
+            &normal_vec,
+            1.0,
+        );
+        // Same as a point light at 45 degrees, but without needing a position.
+        let expected = 0.1 + 0.9 * 2.0_f64.sqrt() / 2.0 + 0.0;
+        assert_eq!(result, Color::new(expected, expected, expected));
+    }
+
+    #[test]
+    fn point_lights_are_not_attenuated_by_default() {
+        let light = Light::Point(PointLight::new(point(0.0, 0.0, 0.0), Color::white()));
+        for distance in [0.0, 1.0, 100.0] {
+            assert_eq!(light.attenuation(distance), 1.0);
+        }
+    }
+
+    #[test]
+    fn computing_attenuation() {
+        let examples = [
+            (Attenuation::new(1.0, 0.5, 0.0), 2.0, 0.5),
+            (Attenuation::new(1.0, 0.0, 0.25), 2.0, 0.5),
+            (Attenuation::new(2.0, 1.0, 1.0), 1.0, 0.25),
+            (Attenuation::inverse_square(), 4.0, 0.0625),
+        ];
+        for (attenuation, distance, expected) in examples {
+            assert_eq!(attenuation.at(distance), expected);
+        }
+    }
+
+    #[test]
+    fn attenuation_only_dims_diffuse_and_specular() {
+        let m = Material::default();
+        let eye_vec = vector(0.0, 0.0, -1.0);
+        let normal_vec = vector(0.0, 0.0, -1.0);
+        let light = Light::Point(
+            PointLight::new(point(0.0, 0.0, -10.0), Color::white())
+                .attenuation(Attenuation::new(1.0, 0.1, 0.0)),
+        );
+        let result = lighting(
+            &m,
+            &sphere(), // Unused
+            &light,
+            &point(0.0, 0.0, 0.0),
+            &eye_vec,
+            &normal_vec,
+            1.0,
+        );
+        // Ambient + (diffuse + specular) at half strength
+        let expected = 0.1 + (0.9 + 0.9) * 0.5;
+        assert_eq!(result, Color::new(expected, expected, expected));
+    }
 }
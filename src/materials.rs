@@ -1,4 +1,4 @@
-use crate::{color::Color, patterns::Stripe};
+use crate::{color::Color, patterns::Pattern};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Material {
@@ -7,21 +7,29 @@ pub struct Material {
     pub diffuse: f64,
     pub specular: f64,
     pub shininess: f64,
-    pub pattern: Option<Stripe>,
+    pub reflective: f64,
+    pub transparency: f64,
+    pub refractive_index: f64,
+    pub pattern: Option<Pattern>,
 }
 
-impl Material {
-    pub fn default() -> Material {
+impl Default for Material {
+    fn default() -> Self {
         Material {
             color: Color::new(1.0, 1.0, 1.0),
             ambient: 0.1,
             diffuse: 0.9,
             specular: 0.9,
             shininess: 200.0,
+            reflective: 0.0,
+            transparency: 0.0,
+            refractive_index: 1.0,
             pattern: None,
         }
     }
+}
 
+impl Material {
     pub fn color(mut self, c: Color) -> Material {
         self.color = c;
         self
@@ -47,7 +55,22 @@ impl Material {
         self
     }
 
-    pub fn pattern(mut self, p: Stripe) -> Material {
+    pub fn reflective(mut self, r: f64) -> Material {
+        self.reflective = r;
+        self
+    }
+
+    pub fn transparency(mut self, t: f64) -> Material {
+        self.transparency = t;
+        self
+    }
+
+    pub fn refractive_index(mut self, r: f64) -> Material {
+        self.refractive_index = r;
+        self
+    }
+
+    pub fn pattern(mut self, p: Pattern) -> Material {
         self.pattern = Some(p);
         self
     }
@@ -57,8 +80,8 @@ impl Material {
 mod tests {
     use crate::{
         color::Color,
-        light::{lighting, PointLight},
-        patterns::Stripe,
+        light::{lighting, Light, PointLight},
+        patterns::stripe,
         sphere::sphere,
         tuple::{point, vector},
     };
@@ -73,6 +96,9 @@ mod tests {
         assert_eq!(m.diffuse, 0.9);
         assert_eq!(m.specular, 0.9);
         assert_eq!(m.shininess, 200.0);
+        assert_eq!(m.reflective, 0.0);
+        assert_eq!(m.transparency, 0.0);
+        assert_eq!(m.refractive_index, 1.0);
     }
 
     #[test]
@@ -81,7 +107,10 @@ mod tests {
         let position = point(0.0, 0.0, 0.0);
         let eye_vec = vector(0.0, 0.0, -1.0);
         let normal_vec = vector(0.0, 0.0, -1.0);
-        let light = PointLight::new(point(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
+        let light = Light::Point(PointLight::new(
+            point(0.0, 0.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        ));
         let result = lighting(
             &m,
             &sphere(), // Unused
@@ -89,7 +118,7 @@ mod tests {
             &position,
             &eye_vec,
             &normal_vec,
-            false,
+            1.0,
         );
         // Ambient + diffuse + specular
         let expected = 0.1 + 0.9 + 0.9;
@@ -102,7 +131,10 @@ mod tests {
         let position = point(0.0, 0.0, 0.0);
         let eye_vec = vector(0.0, 2.0_f64.sqrt() / 2.0, -2.0_f64.sqrt() / 2.0);
         let normal_vec = vector(0.0, 0.0, -1.0);
-        let light = PointLight::new(point(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
+        let light = Light::Point(PointLight::new(
+            point(0.0, 0.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        ));
         let result = lighting(
             &m,
             &sphere(), // Unused
@@ -110,7 +142,7 @@ mod tests {
             &position,
             &eye_vec,
             &normal_vec,
-            false,
+            1.0,
         );
         // Ambient + diffuse + specular
         let expected = 0.1 + 0.9 + 0.0;
@@ -123,7 +155,10 @@ mod tests {
         let position = point(0.0, 0.0, 0.0);
         let eye_vec = vector(0.0, 0.0, -1.0);
         let normal_vec = vector(0.0, 0.0, -1.0);
-        let light = PointLight::new(point(0.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0));
+        let light = Light::Point(PointLight::new(
+            point(0.0, 10.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        ));
         let result = lighting(
             &m,
             &sphere(), // Unused
@@ -131,7 +166,7 @@ mod tests {
             &position,
             &eye_vec,
             &normal_vec,
-            false,
+            1.0,
         );
         // Ambient + diffuse + specular
         let expected = 0.1 + 0.9 * 2.0_f64.sqrt() / 2.0 + 0.0;
@@ -144,7 +179,10 @@ mod tests {
         let position = point(0.0, 0.0, 0.0);
         let eye_vec = vector(0.0, -2.0_f64.sqrt() / 2.0, -2.0_f64.sqrt() / 2.0);
         let normal_vec = vector(0.0, 0.0, -1.0);
-        let light = PointLight::new(point(0.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0));
+        let light = Light::Point(PointLight::new(
+            point(0.0, 10.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        ));
         let result = lighting(
             &m,
             &sphere(), // Unused
@@ -152,7 +190,7 @@ mod tests {
             &position,
             &eye_vec,
             &normal_vec,
-            false,
+            1.0,
         );
         // Ambient + diffuse + specular
         let expected = 0.1 + 0.9 * 2.0_f64.sqrt() / 2.0 + 0.9;
@@ -165,7 +203,10 @@ mod tests {
         let position = point(0.0, 0.0, 0.0);
         let eye_vec = vector(0.0, 0.0, -1.0);
         let normal_vec = vector(0.0, 0.0, -1.0);
-        let light = PointLight::new(point(0.0, 0.0, 10.0), Color::new(1.0, 1.0, 1.0));
+        let light = Light::Point(PointLight::new(
+            point(0.0, 0.0, 10.0),
+            Color::new(1.0, 1.0, 1.0),
+        ));
         let result = lighting(
             &m,
             &sphere(), // Unused
@@ -173,7 +214,7 @@ mod tests {
             &position,
             &eye_vec,
             &normal_vec,
-            false,
+            1.0,
         );
         // Ambient + diffuse + specular
         let expected = 0.1 + 0.0 + 0.0;
@@ -186,7 +227,10 @@ mod tests {
         let position = point(0.0, 0.0, 0.0);
         let eye_vec = vector(0.0, 0.0, -1.0);
         let normal_vec = vector(0.0, 0.0, -1.0);
-        let light = PointLight::new(point(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
+        let light = Light::Point(PointLight::new(
+            point(0.0, 0.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        ));
         let result = lighting(
             &m,
             &sphere(), // Unused
@@ -194,7 +238,7 @@ mod tests {
             &position,
             &eye_vec,
             &normal_vec,
-            true,
+            0.0,
         );
         // Ambient + diffuse + specular
         let expected = 0.1 + 0.0 + 0.0;
@@ -207,10 +251,13 @@ mod tests {
             .ambient(1.0)
             .diffuse(0.0)
             .specular(0.0)
-            .pattern(Stripe::new(Color::white(), Color::black()));
+            .pattern(stripe(Color::white(), Color::black()));
         let eye_vec = vector(0.0, 0.0, -1.0);
         let normal_vec = vector(0.0, 0.0, -1.0);
-        let light = PointLight::new(point(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
+        let light = Light::Point(PointLight::new(
+            point(0.0, 0.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        ));
         let c1 = lighting(
             &m,
             &sphere(), // Unused
@@ -218,7 +265,7 @@ mod tests {
             &point(0.9, 0.0, 0.0),
             &eye_vec,
             &normal_vec,
-            false,
+            1.0,
         );
         let c2 = lighting(
             &m,
@@ -227,7 +274,7 @@ mod tests {
             &point(1.1, 0.0, 0.0),
             &eye_vec,
             &normal_vec,
-            false,
+            1.0,
         );
         assert_eq!(c1, Color::white());
         assert_eq!(c2, Color::black());
@@ -479,7 +479,7 @@ mod tests {
             ],
         );
         assert_eq!(determinant(&a), -2120.0);
-        assert_eq!(invertible(&a), true);
+        assert!(invertible(&a));
     }
 
     #[test]
@@ -492,7 +492,7 @@ mod tests {
             ],
         );
         assert_eq!(determinant(&a), 0.0);
-        assert_eq!(invertible(&a), false);
+        assert!(!invertible(&a));
     }
 
     #[test]
@@ -0,0 +1,247 @@
+// Ken Perlin's "improved noise": smooth pseudo-random values that vary continuously
+// through space, roughly in the range -1.0 to 1.0, and are 0.0 at every integer point.
+
+// Perlin's reference permutation of 0..=255.
+const PERMUTATION: [u8; 256] = [
+    151, 160, 137, 91, 90, 15, 131, 13, 201, 95, 96, 53, 194, 233, 7, 225, 140, 36, 103, 30, 69,
+    142, 8, 99, 37, 240, 21, 10, 23, 190, 6, 148, 247, 120, 234, 75, 0, 26, 197, 62, 94, 252, 219,
+    203, 117, 35, 11, 32, 57, 177, 33, 88, 237, 149, 56, 87, 174, 20, 125, 136, 171, 168, 68, 175,
+    74, 165, 71, 134, 139, 48, 27, 166, 77, 146, 158, 231, 83, 111, 229, 122, 60, 211, 133, 230,
+    220, 105, 92, 41, 55, 46, 245, 40, 244, 102, 143, 54, 65, 25, 63, 161, 1, 216, 80, 73, 209, 76,
+    132, 187, 208, 89, 18, 169, 200, 196, 135, 130, 116, 188, 159, 86, 164, 100, 109, 198, 173,
+    186, 3, 64, 52, 217, 226, 250, 124, 123, 5, 202, 38, 147, 118, 126, 255, 82, 85, 212, 207, 206,
+    59, 227, 47, 16, 58, 17, 182, 189, 28, 42, 223, 183, 170, 213, 119, 248, 152, 2, 44, 154, 163,
+    70, 221, 153, 101, 155, 167, 43, 172, 9, 129, 22, 39, 253, 19, 98, 108, 110, 79, 113, 224, 232,
+    178, 185, 112, 104, 218, 246, 97, 228, 251, 34, 242, 193, 238, 210, 144, 12, 191, 179, 162,
+    241, 81, 51, 145, 235, 249, 14, 239, 107, 49, 192, 214, 31, 181, 199, 106, 157, 184, 84, 204,
+    176, 115, 121, 50, 45, 127, 4, 150, 254, 138, 236, 205, 93, 222, 114, 67, 29, 24, 72, 243, 141,
+    128, 195, 78, 66, 215, 61, 156, 180,
+];
+
+// A pseudo-random number in [0, 1) that depends only on the values given, by feeding each
+// in turn through the SplitMix64 finaliser. Anything sampled this way, such as the offsets
+// for anti-aliasing and soft shadows, comes out the same on every run and however many
+// threads share the work.
+pub fn hash_to_unit(values: &[u64]) -> f64 {
+    let mut hash = 0x9e37_79b9_7f4a_7c15_u64;
+    for value in values {
+        hash = (hash ^ value).wrapping_add(0x9e37_79b9_7f4a_7c15);
+        hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        hash ^= hash >> 31;
+    }
+    // Top 53 bits fill the mantissa exactly.
+    (hash >> 11) as f64 / (1_u64 << 53) as f64
+}
+
+// Perlin noise using Perlin's own permutation.
+pub fn perlin(x: f64, y: f64, z: f64) -> f64 {
+    noise(&PERMUTATION, x, y, z)
+}
+
+// A seeded source of Perlin noise. Different seeds give unrelated noise, and the same seed
+// always gives the same noise.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Perlin {
+    permutation: [u8; 256],
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Perlin {
+        // Fisher-Yates shuffle.
+        let mut permutation = [0; 256];
+        for (i, p) in permutation.iter_mut().enumerate() {
+            *p = i as u8;
+        }
+        for i in (1..256).rev() {
+            let j = (hash_to_unit(&[seed, i as u64]) * (i + 1) as f64) as usize;
+            permutation.swap(i, j);
+        }
+        Perlin { permutation }
+    }
+
+    pub fn noise(&self, x: f64, y: f64, z: f64) -> f64 {
+        noise(&self.permutation, x, y, z)
+    }
+
+    // Fractal Brownian motion: layers of noise at doubling frequency and halving strength,
+    // which gives detail at every scale like clouds. Roughly -1.0 to 1.0.
+    pub fn fbm(&self, x: f64, y: f64, z: f64, octaves: u32) -> f64 {
+        self.octaves(x, y, z, octaves, |n| n)
+    }
+
+    // Like fbm but using the size of the noise, which gives sharp creases where it
+    // crosses zero, like veins in marble. Roughly 0.0 to 1.0.
+    pub fn turbulence(&self, x: f64, y: f64, z: f64, octaves: u32) -> f64 {
+        self.octaves(x, y, z, octaves, f64::abs)
+    }
+
+    fn octaves(&self, x: f64, y: f64, z: f64, octaves: u32, f: impl Fn(f64) -> f64) -> f64 {
+        let mut total = 0.0;
+        let mut frequency = 1.0;
+        let mut amplitude = 1.0;
+        let mut max = 0.0;
+        for _ in 0..octaves.max(1) {
+            total += f(self.noise(x * frequency, y * frequency, z * frequency)) * amplitude;
+            max += amplitude;
+            frequency *= 2.0;
+            amplitude *= 0.5;
+        }
+        // Keep the result in the same range however many octaves are used.
+        total / max
+    }
+}
+
+fn noise(permutation: &[u8; 256], x: f64, y: f64, z: f64) -> f64 {
+    let p = |i: usize| permutation[i & 255] as usize;
+
+    // The unit cube containing the point, wrapped to the size of the table.
+    let (xi, yi, zi) = (
+        x.floor() as i64 as usize & 255,
+        y.floor() as i64 as usize & 255,
+        z.floor() as i64 as usize & 255,
+    );
+    // Where the point is within that cube.
+    let (x, y, z) = (x - x.floor(), y - y.floor(), z - z.floor());
+    let (u, v, w) = (fade(x), fade(y), fade(z));
+
+    // Hash the coordinates of the cube's eight corners.
+    let a = p(xi) + yi;
+    let aa = p(a) + zi;
+    let ab = p(a + 1) + zi;
+    let b = p(xi + 1) + yi;
+    let ba = p(b) + zi;
+    let bb = p(b + 1) + zi;
+
+    // Blend the contributions from each corner.
+    lerp(
+        w,
+        lerp(
+            v,
+            lerp(u, grad(p(aa), x, y, z), grad(p(ba), x - 1.0, y, z)),
+            lerp(
+                u,
+                grad(p(ab), x, y - 1.0, z),
+                grad(p(bb), x - 1.0, y - 1.0, z),
+            ),
+        ),
+        lerp(
+            v,
+            lerp(
+                u,
+                grad(p(aa + 1), x, y, z - 1.0),
+                grad(p(ba + 1), x - 1.0, y, z - 1.0),
+            ),
+            lerp(
+                u,
+                grad(p(ab + 1), x, y - 1.0, z - 1.0),
+                grad(p(bb + 1), x - 1.0, y - 1.0, z - 1.0),
+            ),
+        ),
+    )
+}
+
+// 6t^5 - 15t^4 + 10t^3, which eases in and out so the noise has no creases at cube edges.
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    a + t * (b - a)
+}
+
+// Dot product of (x, y, z) with one of 12 gradient directions picked by the hash.
+fn grad(hash: usize, x: f64, y: f64, z: f64) -> f64 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn perlin_noise_is_zero_at_integer_points() {
+        for (x, y, z) in [(0.0, 0.0, 0.0), (1.0, 2.0, 3.0), (-4.0, 7.0, -1.0)] {
+            assert_eq!(perlin(x, y, z), 0.0);
+        }
+    }
+
+    #[test]
+    fn perlin_noise_is_smooth_and_bounded() {
+        let mut previous = perlin(0.0, 0.3, 0.7);
+        let mut any_nonzero = false;
+        for i in 1..2000 {
+            let x = i as f64 * 0.01;
+            let n = perlin(x, 0.3, 0.7);
+            assert!((-1.0..=1.0).contains(&n));
+            // Small steps only ever make small changes.
+            assert!((n - previous).abs() < 0.05);
+            any_nonzero |= n.abs() > 0.1;
+            previous = n;
+        }
+        assert!(any_nonzero);
+    }
+
+    #[test]
+    fn perlin_noise_is_repeatable() {
+        assert_eq!(perlin(1.5, -2.25, 3.75), perlin(1.5, -2.25, 3.75));
+        assert_ne!(perlin(1.5, -2.25, 3.75), perlin(1.5, -2.25, 3.5));
+    }
+
+    #[test]
+    fn hashed_values_are_repeatable_and_in_range() {
+        assert_eq!(hash_to_unit(&[1, 2, 3]), hash_to_unit(&[1, 2, 3]));
+        assert_ne!(hash_to_unit(&[1, 2, 3]), hash_to_unit(&[3, 2, 1]));
+        for i in 0..1000 {
+            let v = hash_to_unit(&[i]);
+            assert!((0.0..1.0).contains(&v));
+        }
+    }
+
+    #[test]
+    fn seeded_noise_is_deterministic() {
+        let a = Perlin::new(42);
+        let b = Perlin::new(42);
+        let c = Perlin::new(43);
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(a.noise(0.3, 1.7, -2.2), b.noise(0.3, 1.7, -2.2));
+        assert_ne!(a.noise(0.3, 1.7, -2.2), c.noise(0.3, 1.7, -2.2));
+    }
+
+    #[test]
+    fn seeded_permutation_contains_every_value_once() {
+        let mut seen = [false; 256];
+        for p in Perlin::new(7).permutation {
+            assert!(!seen[p as usize]);
+            seen[p as usize] = true;
+        }
+    }
+
+    #[test]
+    fn fbm_and_turbulence_stay_in_range() {
+        let noise = Perlin::new(1);
+        for i in 0..500 {
+            let (x, y, z) = (i as f64 * 0.137, i as f64 * 0.071, i as f64 * -0.053);
+            assert!((-1.0..=1.0).contains(&noise.fbm(x, y, z, 6)));
+            assert!((0.0..=1.0).contains(&noise.turbulence(x, y, z, 6)));
+        }
+    }
+
+    #[test]
+    fn one_octave_is_plain_noise() {
+        let noise = Perlin::new(5);
+        let (x, y, z) = (0.4, 1.3, 2.9);
+        assert_eq!(noise.fbm(x, y, z, 1), noise.noise(x, y, z));
+        assert_eq!(noise.turbulence(x, y, z, 1), noise.noise(x, y, z).abs());
+    }
+}
@@ -0,0 +1,524 @@
+use std::fmt;
+
+use crate::{
+    group::group,
+    shape::Shape,
+    triangle::{smooth_triangle, triangle},
+    tuple::{point, vector, Tuple},
+};
+
+#[derive(Debug, PartialEq)]
+pub enum ObjError {
+    // A statement we understand, but whose arguments couldn't be parsed.
+    Malformed { line: usize, message: String },
+    // A face refers to a vertex, texture vertex or normal that doesn't exist.
+    IndexOutOfRange { line: usize, index: i64 },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Malformed { line, message } => {
+                write!(f, "line {}: {}", line, message)
+            }
+            ObjError::IndexOutOfRange { line, index } => {
+                write!(f, "line {}: index {} is out of range", line, index)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+#[derive(Debug)]
+pub struct ObjGroup {
+    pub name: String,
+    pub triangles: Vec<Shape>,
+}
+
+#[derive(Debug)]
+pub struct ObjFile {
+    pub vertices: Vec<Tuple>,
+    pub normals: Vec<Tuple>,
+    pub texture_vertices: Vec<(f64, f64)>,
+    // Faces that appear before any g or o statement.
+    pub default_group: Vec<Shape>,
+    pub groups: Vec<ObjGroup>,
+    // Line number and content of every line that was skipped.
+    pub ignored: Vec<(usize, String)>,
+}
+
+impl ObjFile {
+    pub fn group(&self, name: &str) -> Option<&[Shape]> {
+        self.groups
+            .iter()
+            .find(|g| g.name == name)
+            .map(|g| g.triangles.as_slice())
+    }
+
+    // Builds a single group holding the ungrouped triangles, plus a child group per named group.
+    pub fn to_group(&self) -> Shape {
+        let mut result = group();
+        for t in &self.default_group {
+            result.add_child(t.clone());
+        }
+        for g in &self.groups {
+            let mut child = group();
+            for t in &g.triangles {
+                child.add_child(t.clone());
+            }
+            result.add_child(child);
+        }
+        result
+    }
+
+    fn current_group(&mut self, current: &Option<usize>) -> &mut Vec<Shape> {
+        match current {
+            Some(index) => &mut self.groups[*index].triangles,
+            None => &mut self.default_group,
+        }
+    }
+}
+
+// One corner of a face, as indices into the vertex and normal lists.
+struct FaceVertex {
+    vertex: usize,
+    normal: Option<usize>,
+}
+
+pub fn parse_obj_file(contents: &str) -> Result<ObjFile, ObjError> {
+    let mut obj = ObjFile {
+        vertices: vec![],
+        normals: vec![],
+        texture_vertices: vec![],
+        default_group: vec![],
+        groups: vec![],
+        ignored: vec![],
+    };
+    let mut current_group = None;
+
+    for (number, text) in contents.lines().enumerate() {
+        let line = number + 1;
+        let mut parts = text.split_whitespace();
+        let Some(keyword) = parts.next() else {
+            continue;
+        };
+        let args = parts.collect::<Vec<_>>();
+
+        match keyword {
+            "v" => {
+                // The optional weight (w) is only used by rational curves.
+                let [x, y, z, _] = parse_floats::<4>(&args, 3, line)?;
+                obj.vertices.push(point(x, y, z));
+            }
+            "vn" => {
+                let [x, y, z] = parse_floats::<3>(&args, 3, line)?;
+                obj.normals.push(vector(x, y, z));
+            }
+            "vt" => {
+                // v defaults to 0.0, and the optional third (w) coordinate isn't needed
+                // for 2D textures.
+                let [u, v, _] = parse_floats::<3>(&args, 1, line)?;
+                obj.texture_vertices.push((u, v));
+            }
+            "f" => {
+                let corners = args
+                    .iter()
+                    .map(|a| parse_face_vertex(a, &obj, line))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if corners.len() < 3 {
+                    return Err(ObjError::Malformed {
+                        line,
+                        message: format!("face needs at least 3 vertices, got {}", corners.len()),
+                    });
+                }
+                let triangles = fan_triangulation(&obj, &corners);
+                obj.current_group(&current_group).extend(triangles);
+            }
+            "g" | "o" => {
+                let name = args.join(" ");
+                let index = match obj.groups.iter().position(|g| g.name == name) {
+                    Some(index) => index,
+                    None => {
+                        obj.groups.push(ObjGroup {
+                            name,
+                            triangles: vec![],
+                        });
+                        obj.groups.len() - 1
+                    }
+                };
+                current_group = Some(index);
+            }
+            _ => obj.ignored.push((line, text.to_string())),
+        }
+    }
+
+    Ok(obj)
+}
+
+// Parses between required and N numbers. Any that are left out are 0.0.
+fn parse_floats<const N: usize>(
+    args: &[&str],
+    required: usize,
+    line: usize,
+) -> Result<[f64; N], ObjError> {
+    if !(required..=N).contains(&args.len()) {
+        let expected = if required == N {
+            N.to_string()
+        } else {
+            format!("{} to {}", required, N)
+        };
+        return Err(ObjError::Malformed {
+            line,
+            message: format!("expected {} numbers, got {}", expected, args.len()),
+        });
+    }
+
+    let mut result = [0.0; N];
+    for (value, arg) in result.iter_mut().zip(args) {
+        *value = arg.parse().map_err(|_| ObjError::Malformed {
+            line,
+            message: format!("'{}' is not a number", arg),
+        })?;
+    }
+    Ok(result)
+}
+
+// Handles the v, v/vt, v//vn and v/vt/vn forms.
+fn parse_face_vertex(arg: &str, obj: &ObjFile, line: usize) -> Result<FaceVertex, ObjError> {
+    let mut indices = arg.split('/');
+    let vertex = indices.next().unwrap_or_default();
+    let vertex = resolve_index(vertex, obj.vertices.len(), line)?;
+
+    if let Some(texture) = indices.next().filter(|t| !t.is_empty()) {
+        resolve_index(texture, obj.texture_vertices.len(), line)?;
+    }
+
+    let normal = match indices.next().filter(|n| !n.is_empty()) {
+        Some(normal) => Some(resolve_index(normal, obj.normals.len(), line)?),
+        None => None,
+    };
+
+    if indices.next().is_some() {
+        return Err(ObjError::Malformed {
+            line,
+            message: format!("'{}' has too many indices", arg),
+        });
+    }
+
+    Ok(FaceVertex { vertex, normal })
+}
+
+// OBJ indices start at 1, and negative ones count back from the end of the list so far.
+fn resolve_index(text: &str, count: usize, line: usize) -> Result<usize, ObjError> {
+    let index: i64 = text.parse().map_err(|_| ObjError::Malformed {
+        line,
+        message: format!("'{}' is not an index", text),
+    })?;
+
+    let resolved = if index < 0 {
+        count as i64 + index
+    } else {
+        index - 1
+    };
+
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(ObjError::IndexOutOfRange { line, index });
+    }
+    Ok(resolved as usize)
+}
+
+// Splits a convex polygon into triangles that all share its first vertex.
+fn fan_triangulation(obj: &ObjFile, corners: &[FaceVertex]) -> Vec<Shape> {
+    let mut triangles = vec![];
+
+    for i in 1..corners.len() - 1 {
+        let (a, b, c) = (&corners[0], &corners[i], &corners[i + 1]);
+        let p1 = obj.vertices[a.vertex].clone();
+        let p2 = obj.vertices[b.vertex].clone();
+        let p3 = obj.vertices[c.vertex].clone();
+
+        let t = match (a.normal, b.normal, c.normal) {
+            (Some(n1), Some(n2), Some(n3)) => smooth_triangle(
+                p1,
+                p2,
+                p3,
+                obj.normals[n1].clone(),
+                obj.normals[n2].clone(),
+                obj.normals[n3].clone(),
+            ),
+            _ => triangle(p1, p2, p3),
+        };
+        triangles.push(t);
+    }
+
+    triangles
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        shape::ShapeType,
+        triangle::{SmoothTriangle, Triangle},
+    };
+
+    use super::*;
+
+    fn as_triangle(s: &Shape) -> &Triangle {
+        match &s.shape_type {
+            ShapeType::Triangle(t) => t,
+            _ => panic!("not a triangle"),
+        }
+    }
+
+    fn as_smooth_triangle(s: &Shape) -> &SmoothTriangle {
+        match &s.shape_type {
+            ShapeType::SmoothTriangle(t) => t,
+            _ => panic!("not a smooth triangle"),
+        }
+    }
+
+    #[test]
+    fn ignoring_unrecognised_lines() {
+        let gibberish = "There was a young lady named Bright
+who traveled much faster than light.
+She set out one day
+in a relative way,
+and came back the previous night.";
+        let obj = parse_obj_file(gibberish).unwrap();
+        assert_eq!(obj.ignored.len(), 5);
+        assert_eq!(
+            obj.ignored[1],
+            (2, "who traveled much faster than light.".to_string())
+        );
+    }
+
+    #[test]
+    fn vertex_records() {
+        let file = "v -1 1 0
+v -1.0000 0.5000 0.0000
+v 1 0 0
+v 1 1 0";
+        let obj = parse_obj_file(file).unwrap();
+        assert_eq!(obj.vertices[0], point(-1.0, 1.0, 0.0));
+        assert_eq!(obj.vertices[1], point(-1.0, 0.5, 0.0));
+        assert_eq!(obj.vertices[2], point(1.0, 0.0, 0.0));
+        assert_eq!(obj.vertices[3], point(1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn vertex_with_a_weight() {
+        let file = "v 1 2 3 0.5
+v 1 2";
+        let err = parse_obj_file(file).unwrap_err();
+        assert_eq!(
+            err,
+            ObjError::Malformed {
+                line: 2,
+                message: "expected 3 to 4 numbers, got 2".to_string()
+            }
+        );
+        let obj = parse_obj_file("v 1 2 3 0.5").unwrap();
+        assert_eq!(obj.vertices[0], point(1.0, 2.0, 3.0));
+    }
+
+    #[test]
+    fn texture_vertex_with_only_u() {
+        let file = "vt 0.25
+vt 0.5 0.75
+vt 1 0.5 0";
+        let obj = parse_obj_file(file).unwrap();
+        assert_eq!(
+            obj.texture_vertices,
+            vec![(0.25, 0.0), (0.5, 0.75), (1.0, 0.5)]
+        );
+        assert!(matches!(
+            parse_obj_file("vt"),
+            Err(ObjError::Malformed { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn parsing_triangle_faces() {
+        let file = "v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+
+f 1 2 3
+f 1 3 4";
+        let obj = parse_obj_file(file).unwrap();
+        let t1 = as_triangle(&obj.default_group[0]);
+        let t2 = as_triangle(&obj.default_group[1]);
+        assert_eq!(t1.p1, obj.vertices[0]);
+        assert_eq!(t1.p2, obj.vertices[1]);
+        assert_eq!(t1.p3, obj.vertices[2]);
+        assert_eq!(t2.p1, obj.vertices[0]);
+        assert_eq!(t2.p2, obj.vertices[2]);
+        assert_eq!(t2.p3, obj.vertices[3]);
+    }
+
+    #[test]
+    fn triangulating_polygons() {
+        let file = "v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+v 0 2 0
+
+f 1 2 3 4 5";
+        let obj = parse_obj_file(file).unwrap();
+        assert_eq!(obj.default_group.len(), 3);
+        let t1 = as_triangle(&obj.default_group[0]);
+        let t2 = as_triangle(&obj.default_group[1]);
+        let t3 = as_triangle(&obj.default_group[2]);
+        assert_eq!(t1.p1, obj.vertices[0]);
+        assert_eq!(t1.p2, obj.vertices[1]);
+        assert_eq!(t1.p3, obj.vertices[2]);
+        assert_eq!(t2.p1, obj.vertices[0]);
+        assert_eq!(t2.p2, obj.vertices[2]);
+        assert_eq!(t2.p3, obj.vertices[3]);
+        assert_eq!(t3.p1, obj.vertices[0]);
+        assert_eq!(t3.p2, obj.vertices[3]);
+        assert_eq!(t3.p3, obj.vertices[4]);
+    }
+
+    #[test]
+    fn triangles_in_groups() {
+        let file = "v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+
+g FirstGroup
+f 1 2 3
+o SecondGroup
+f 1 3 4";
+        let obj = parse_obj_file(file).unwrap();
+        assert!(obj.default_group.is_empty());
+        let g1 = obj.group("FirstGroup").unwrap();
+        let g2 = obj.group("SecondGroup").unwrap();
+        assert_eq!(g1.len(), 1);
+        assert_eq!(g2.len(), 1);
+        assert_eq!(as_triangle(&g1[0]).p3, obj.vertices[2]);
+        assert_eq!(as_triangle(&g2[0]).p3, obj.vertices[3]);
+    }
+
+    #[test]
+    fn vertex_normal_records() {
+        let file = "vn 0 0 1
+vn 0.707 0 -0.707
+vn 1 2 3";
+        let obj = parse_obj_file(file).unwrap();
+        assert_eq!(obj.normals[0], vector(0.0, 0.0, 1.0));
+        assert_eq!(obj.normals[1], vector(0.707, 0.0, -0.707));
+        assert_eq!(obj.normals[2], vector(1.0, 2.0, 3.0));
+    }
+
+    #[test]
+    fn faces_with_normals() {
+        let file = "v 0 1 0
+v -1 0 0
+v 1 0 0
+
+vt 0.5 1
+vt 0 0 0
+vt 1 0
+
+vn -1 0 0
+vn 1 0 0
+vn 0 1 0
+
+f 1//3 2//1 3//2
+f 1/1/3 2/2/1 3/3/2";
+        let obj = parse_obj_file(file).unwrap();
+        assert_eq!(obj.texture_vertices.len(), 3);
+        let t1 = as_smooth_triangle(&obj.default_group[0]);
+        let t2 = as_smooth_triangle(&obj.default_group[1]);
+        assert_eq!(t1.p1, obj.vertices[0]);
+        assert_eq!(t1.p2, obj.vertices[1]);
+        assert_eq!(t1.p3, obj.vertices[2]);
+        assert_eq!(t1.n1, obj.normals[2]);
+        assert_eq!(t1.n2, obj.normals[0]);
+        assert_eq!(t1.n3, obj.normals[1]);
+        assert_eq!(t2, t1);
+    }
+
+    #[test]
+    fn negative_indices_count_from_the_end() {
+        let file = "v 0 1 0
+v -1 0 0
+v 1 0 0
+f -3 -2 -1";
+        let obj = parse_obj_file(file).unwrap();
+        let t = as_triangle(&obj.default_group[0]);
+        assert_eq!(t.p1, obj.vertices[0]);
+        assert_eq!(t.p3, obj.vertices[2]);
+    }
+
+    #[test]
+    fn malformed_vertex_is_an_error() {
+        let file = "v 1 2 3
+v 1 two 3";
+        let err = parse_obj_file(file).unwrap_err();
+        assert_eq!(
+            err,
+            ObjError::Malformed {
+                line: 2,
+                message: "'two' is not a number".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn face_with_out_of_range_index_is_an_error() {
+        let file = "v 0 1 0
+v -1 0 0
+v 1 0 0
+f 1 2 4";
+        let err = parse_obj_file(file).unwrap_err();
+        assert_eq!(err, ObjError::IndexOutOfRange { line: 4, index: 4 });
+    }
+
+    #[test]
+    fn face_with_out_of_range_normal_is_an_error() {
+        let file = "v 0 1 0
+v -1 0 0
+v 1 0 0
+vn 0 0 1
+f 1//1 2//1 3//2";
+        let err = parse_obj_file(file).unwrap_err();
+        assert_eq!(err, ObjError::IndexOutOfRange { line: 5, index: 2 });
+    }
+
+    #[test]
+    fn face_with_too_few_vertices_is_an_error() {
+        let file = "v 0 1 0
+v -1 0 0
+f 1 2";
+        let err = parse_obj_file(file).unwrap_err();
+        assert!(matches!(err, ObjError::Malformed { line: 3, .. }));
+    }
+
+    #[test]
+    fn converting_obj_file_to_a_group() {
+        let file = "v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+f 1 2 3
+g FirstGroup
+f 1 2 3
+g SecondGroup
+f 1 3 4";
+        let obj = parse_obj_file(file).unwrap();
+        let g = obj.to_group();
+        assert_eq!(g.children().len(), 3);
+        assert_eq!(g.children()[0], obj.default_group[0]);
+        assert_eq!(g.children()[1].children(), obj.group("FirstGroup").unwrap());
+        assert_eq!(
+            g.children()[2].children(),
+            obj.group("SecondGroup").unwrap()
+        );
+    }
+}
@@ -1,47 +1,386 @@
+use std::{f64::consts::PI, sync::Arc};
+
 use crate::{
+    canvas::Canvas,
     color::Color,
+    cylinder::Cylinder,
     matrix::{inverse, Matrix},
+    noise::Perlin,
+    plane::Plane,
     shape::Shape,
+    sphere::Sphere,
     tuple::Tuple,
 };
 
 #[derive(Clone, Debug, PartialEq)]
-pub struct Stripe {
-    a: Color,
-    b: Color,
+pub enum PatternType {
+    // The same color everywhere. Mostly useful as part of other patterns.
+    Solid(Color),
+    // Alternates between a and b with every unit of x.
+    Stripe {
+        a: Box<Pattern>,
+        b: Box<Pattern>,
+    },
+    // Blends from a to b across every unit of x.
+    Gradient {
+        a: Box<Pattern>,
+        b: Box<Pattern>,
+    },
+    // Concentric rings around the y axis.
+    Ring {
+        a: Box<Pattern>,
+        b: Box<Pattern>,
+    },
+    // A 3D checker board of unit cubes.
+    Checker {
+        a: Box<Pattern>,
+        b: Box<Pattern>,
+    },
+    // Blends from a to b across every unit of distance from the y axis.
+    RadialGradient {
+        a: Box<Pattern>,
+        b: Box<Pattern>,
+    },
+    // The average of a and b.
+    Blend {
+        a: Box<Pattern>,
+        b: Box<Pattern>,
+    },
+    // Moves each point by up to scale in each direction, using noise, before looking it up
+    // in the pattern. Makes regular patterns look more natural.
+    Perturbed {
+        pattern: Box<Pattern>,
+        scale: f64,
+        noise: Arc<Perlin>,
+    },
+    // Fractal noise blending between a and b, like clouds or smoke. The noise is shared
+    // for the same reason as a texture's image.
+    Clouds {
+        a: Box<Pattern>,
+        b: Box<Pattern>,
+        noise: Arc<Perlin>,
+        octaves: u32,
+    },
+    // Bands of a and b along x, warped by turbulence into veins.
+    Marble {
+        a: Box<Pattern>,
+        b: Box<Pattern>,
+        noise: Arc<Perlin>,
+        octaves: u32,
+        turbulence: f64,
+    },
+    // Rings of a and b around the y axis, warped by turbulence like wood grain.
+    Wood {
+        a: Box<Pattern>,
+        b: Box<Pattern>,
+        noise: Arc<Perlin>,
+        octaves: u32,
+        turbulence: f64,
+    },
+    // An image wrapped onto the surface. Shared, as a material is copied with every
+    // intersection of the shape it belongs to.
+    Texture {
+        canvas: Arc<Canvas>,
+        mapping: UvMapping,
+        wrap: TextureWrap,
+    },
+    // The point's own coordinates as a color, so tests can see which point was looked up.
+    #[cfg(test)]
+    Test,
+}
+
+// How a point in pattern space is flattened into (u, v) texture coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UvMapping {
+    Spherical,
+    Planar,
+    Cylindrical,
+}
+
+// What happens to texture coordinates outside 0.0 to 1.0, which includes the pixels
+// either side of an edge when filtering.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextureWrap {
+    // The image tiles.
+    Repeat,
+    // The edge pixels carry on forever.
+    Clamp,
+}
+
+// Patterns are built out of other patterns, so a plain color can be used anywhere a
+// pattern is expected.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pattern {
+    pub pattern_type: PatternType,
     pub transformation: Matrix,
 }
 
-impl Stripe {
-    pub fn new(a: Color, b: Color) -> Stripe {
-        Stripe {
-            a,
-            b,
+impl From<Color> for Pattern {
+    fn from(color: Color) -> Pattern {
+        solid(color)
+    }
+}
+
+impl Pattern {
+    pub fn new(pattern_type: PatternType) -> Pattern {
+        Pattern {
+            pattern_type,
             transformation: Matrix::identity_4x4(),
         }
     }
 
     fn color_at_point(&self, point: &Tuple) -> Color {
-        if point.x.floor() % 2.0 == 0.0 {
-            self.a
-        } else {
-            self.b
+        match &self.pattern_type {
+            PatternType::Solid(color) => *color,
+            PatternType::Stripe { a, b } => {
+                if point.x.floor() % 2.0 == 0.0 {
+                    a.at_point(point)
+                } else {
+                    b.at_point(point)
+                }
+            }
+            PatternType::Gradient { a, b } => {
+                let (a, b) = (a.at_point(point), b.at_point(point));
+                a + (b - a) * (point.x - point.x.floor())
+            }
+            PatternType::Ring { a, b } => {
+                if (point.x * point.x + point.z * point.z).sqrt().floor() % 2.0 == 0.0 {
+                    a.at_point(point)
+                } else {
+                    b.at_point(point)
+                }
+            }
+            PatternType::Checker { a, b } => {
+                if (point.x.floor() + point.y.floor() + point.z.floor()) % 2.0 == 0.0 {
+                    a.at_point(point)
+                } else {
+                    b.at_point(point)
+                }
+            }
+            PatternType::RadialGradient { a, b } => {
+                let (a, b) = (a.at_point(point), b.at_point(point));
+                let distance = (point.x * point.x + point.z * point.z).sqrt();
+                a + (b - a) * (distance - distance.floor())
+            }
+            PatternType::Blend { a, b } => (a.at_point(point) + b.at_point(point)) * 0.5,
+            PatternType::Perturbed {
+                pattern,
+                scale,
+                noise,
+            } => {
+                // Each axis reads the noise from a different place so they don't all move
+                // together. The offsets aren't whole numbers, as the noise is 0 at every
+                // integer point and the axes would all stop moving there at once.
+                let jitter = |offset: f64| {
+                    noise.noise(point.x + offset, point.y + offset, point.z + offset) * scale
+                };
+                let jittered = Tuple {
+                    x: point.x + jitter(0.31),
+                    y: point.y + jitter(17.57),
+                    z: point.z + jitter(41.83),
+                    w: point.w,
+                };
+                pattern.at_point(&jittered)
+            }
+            PatternType::Clouds {
+                a,
+                b,
+                noise,
+                octaves,
+            } => {
+                let t = (noise.fbm(point.x, point.y, point.z, *octaves) + 1.0) / 2.0;
+                mix(a.at_point(point), b.at_point(point), t)
+            }
+            PatternType::Marble {
+                a,
+                b,
+                noise,
+                octaves,
+                turbulence,
+            } => {
+                let warp = noise.turbulence(point.x, point.y, point.z, *octaves) * turbulence;
+                let t = ((point.x + warp) * PI).sin().abs();
+                mix(a.at_point(point), b.at_point(point), t)
+            }
+            PatternType::Wood {
+                a,
+                b,
+                noise,
+                octaves,
+                turbulence,
+            } => {
+                let warp = noise.turbulence(point.x, point.y, point.z, *octaves) * turbulence;
+                let distance = (point.x * point.x + point.z * point.z).sqrt() + warp;
+                mix(
+                    a.at_point(point),
+                    b.at_point(point),
+                    distance - distance.floor(),
+                )
+            }
+            PatternType::Texture {
+                canvas,
+                mapping,
+                wrap,
+            } => {
+                let (u, v) = match mapping {
+                    UvMapping::Spherical => Sphere::uv_map(point),
+                    UvMapping::Planar => Plane::uv_map(point),
+                    UvMapping::Cylindrical => Cylinder::uv_map(point),
+                };
+                sample_bilinear(canvas, u, v, *wrap)
+            }
+            #[cfg(test)]
+            PatternType::Test => Color::new(point.x, point.y, point.z),
         }
     }
 
-    pub fn at(&self, object: &Shape, point: &Tuple) -> Color {
-        let object_point = inverse(object.transform()) * point;
-        let pattern_point = inverse(&self.transformation) * object_point;
+    // The color at a point given in the space of whatever contains this pattern, which is
+    // either an object or another pattern.
+    fn at_point(&self, point: &Tuple) -> Color {
+        let pattern_point = inverse(&self.transformation) * point.clone();
         self.color_at_point(&pattern_point)
     }
+
+    pub fn at(&self, object: &Shape, point: &Tuple) -> Color {
+        let object_point = object.world_to_object(point);
+        self.at_point(&object_point)
+    }
+}
+
+fn mix(a: Color, b: Color, t: f64) -> Color {
+    a + (b - a) * t
+}
+
+// Blends the four pixels nearest to (u, v), so textures don't look blocky close up.
+// v = 0.0 is the bottom of the image, the opposite of canvas rows.
+fn sample_bilinear(canvas: &Canvas, u: f64, v: f64, wrap: TextureWrap) -> Color {
+    let (width, height) = (canvas.width() as i64, canvas.height() as i64);
+    // Pixel centres are at half coordinates.
+    let x = u * width as f64 - 0.5;
+    let y = (1.0 - v) * height as f64 - 0.5;
+    let (x0, y0) = (x.floor() as i64, y.floor() as i64);
+    let (tx, ty) = (x - x.floor(), y - y.floor());
+
+    let pixel = |x: i64, y: i64| {
+        let (x, y) = match wrap {
+            TextureWrap::Repeat => (x.rem_euclid(width), y.rem_euclid(height)),
+            TextureWrap::Clamp => (x.clamp(0, width - 1), y.clamp(0, height - 1)),
+        };
+        *canvas.pixel_at(x as usize, y as usize)
+    };
+
+    let top = mix(pixel(x0, y0), pixel(x0 + 1, y0), tx);
+    let bottom = mix(pixel(x0, y0 + 1), pixel(x0 + 1, y0 + 1), tx);
+    mix(top, bottom, ty)
+}
+
+#[cfg(test)]
+pub fn test_pattern() -> Pattern {
+    Pattern::new(PatternType::Test)
+}
+
+pub fn solid(color: Color) -> Pattern {
+    Pattern::new(PatternType::Solid(color))
+}
+
+pub fn stripe(a: impl Into<Pattern>, b: impl Into<Pattern>) -> Pattern {
+    Pattern::new(PatternType::Stripe {
+        a: Box::new(a.into()),
+        b: Box::new(b.into()),
+    })
+}
+
+pub fn gradient(a: impl Into<Pattern>, b: impl Into<Pattern>) -> Pattern {
+    Pattern::new(PatternType::Gradient {
+        a: Box::new(a.into()),
+        b: Box::new(b.into()),
+    })
+}
+
+pub fn ring(a: impl Into<Pattern>, b: impl Into<Pattern>) -> Pattern {
+    Pattern::new(PatternType::Ring {
+        a: Box::new(a.into()),
+        b: Box::new(b.into()),
+    })
+}
+
+pub fn checker(a: impl Into<Pattern>, b: impl Into<Pattern>) -> Pattern {
+    Pattern::new(PatternType::Checker {
+        a: Box::new(a.into()),
+        b: Box::new(b.into()),
+    })
+}
+
+pub fn radial_gradient(a: impl Into<Pattern>, b: impl Into<Pattern>) -> Pattern {
+    Pattern::new(PatternType::RadialGradient {
+        a: Box::new(a.into()),
+        b: Box::new(b.into()),
+    })
+}
+
+pub fn blend(a: impl Into<Pattern>, b: impl Into<Pattern>) -> Pattern {
+    Pattern::new(PatternType::Blend {
+        a: Box::new(a.into()),
+        b: Box::new(b.into()),
+    })
+}
+
+pub fn perturbed(pattern: impl Into<Pattern>, scale: f64, seed: u64) -> Pattern {
+    Pattern::new(PatternType::Perturbed {
+        pattern: Box::new(pattern.into()),
+        scale,
+        noise: Arc::new(Perlin::new(seed)),
+    })
+}
+
+pub fn clouds(a: impl Into<Pattern>, b: impl Into<Pattern>, seed: u64) -> Pattern {
+    Pattern::new(PatternType::Clouds {
+        a: Box::new(a.into()),
+        b: Box::new(b.into()),
+        noise: Arc::new(Perlin::new(seed)),
+        octaves: 6,
+    })
+}
+
+pub fn marble(a: impl Into<Pattern>, b: impl Into<Pattern>, seed: u64) -> Pattern {
+    Pattern::new(PatternType::Marble {
+        a: Box::new(a.into()),
+        b: Box::new(b.into()),
+        noise: Arc::new(Perlin::new(seed)),
+        octaves: 6,
+        turbulence: 2.0,
+    })
+}
+
+pub fn wood(a: impl Into<Pattern>, b: impl Into<Pattern>, seed: u64) -> Pattern {
+    Pattern::new(PatternType::Wood {
+        a: Box::new(a.into()),
+        b: Box::new(b.into()),
+        noise: Arc::new(Perlin::new(seed)),
+        octaves: 3,
+        turbulence: 0.3,
+    })
+}
+
+pub fn texture(canvas: Canvas, mapping: UvMapping, wrap: TextureWrap) -> Pattern {
+    assert!(
+        canvas.width() > 0 && canvas.height() > 0,
+        "a texture needs at least one pixel"
+    );
+    Pattern::new(PatternType::Texture {
+        canvas: Arc::new(canvas),
+        mapping,
+        wrap,
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
         color::Color,
+        plane::plane,
         sphere::sphere,
-        transformations::{scaling, translation},
+        transformations::{rotation_y, scaling, translation},
         tuple::point,
     };
 
@@ -49,14 +388,20 @@ mod tests {
 
     #[test]
     fn creating_stripe_pattern() {
-        let pattern = Stripe::new(Color::white(), Color::black());
-        assert_eq!(pattern.a, Color::white());
-        assert_eq!(pattern.b, Color::black());
+        let pattern = stripe(Color::white(), Color::black());
+        assert_eq!(
+            pattern.pattern_type,
+            PatternType::Stripe {
+                a: Box::new(solid(Color::white())),
+                b: Box::new(solid(Color::black()))
+            }
+        );
+        assert_eq!(pattern.transformation, Matrix::identity_4x4());
     }
 
     #[test]
     fn stripe_is_constant_in_y() {
-        let pattern = Stripe::new(Color::white(), Color::black());
+        let pattern = stripe(Color::white(), Color::black());
         assert_eq!(
             pattern.color_at_point(&point(0.0, 0.0, 0.0)),
             Color::white()
@@ -73,7 +418,7 @@ mod tests {
 
     #[test]
     fn stripe_is_constant_in_z() {
-        let pattern = Stripe::new(Color::white(), Color::black());
+        let pattern = stripe(Color::white(), Color::black());
         assert_eq!(
             pattern.color_at_point(&point(0.0, 0.0, 0.0)),
             Color::white()
@@ -90,7 +435,7 @@ mod tests {
 
     #[test]
     fn stripe_alternates_in_x() {
-        let pattern = Stripe::new(Color::white(), Color::black());
+        let pattern = stripe(Color::white(), Color::black());
         assert_eq!(
             pattern.color_at_point(&point(0.0, 0.0, 0.0)),
             Color::white()
@@ -121,7 +466,7 @@ mod tests {
     fn stripe_with_object_transformation() {
         let mut object = sphere();
         object.set_transform(scaling(2.0, 2.0, 2.0));
-        let pattern = Stripe::new(Color::white(), Color::black());
+        let pattern = stripe(Color::white(), Color::black());
         let c = pattern.at(&object, &point(1.5, 0.0, 0.0));
         assert_eq!(c, Color::white());
     }
@@ -129,7 +474,7 @@ mod tests {
     #[test]
     fn stripe_with_pattern_transformation() {
         let object = sphere();
-        let mut pattern = Stripe::new(Color::white(), Color::black());
+        let mut pattern = stripe(Color::white(), Color::black());
         pattern.transformation = scaling(2.0, 2.0, 2.0);
         let c = pattern.at(&object, &point(1.5, 0.0, 0.0));
         assert_eq!(c, Color::white());
@@ -139,9 +484,299 @@ mod tests {
     fn stripe_with_pattern_and_object_transformation() {
         let mut object = sphere();
         object.set_transform(scaling(2.0, 2.0, 2.0));
-        let mut pattern = Stripe::new(Color::white(), Color::black());
+        let mut pattern = stripe(Color::white(), Color::black());
         pattern.transformation = translation(0.5, 0.0, 0.0);
         let c = pattern.at(&object, &point(2.5, 0.0, 0.0));
         assert_eq!(c, Color::white());
     }
+
+    #[test]
+    fn gradient_linearly_interpolates_between_colors() {
+        let pattern = gradient(Color::white(), Color::black());
+        let examples = [
+            (point(0.0, 0.0, 0.0), Color::white()),
+            (point(0.25, 0.0, 0.0), Color::new(0.75, 0.75, 0.75)),
+            (point(0.5, 0.0, 0.0), Color::new(0.5, 0.5, 0.5)),
+            (point(0.75, 0.0, 0.0), Color::new(0.25, 0.25, 0.25)),
+        ];
+        for (p, expected) in examples {
+            assert_eq!(pattern.color_at_point(&p), expected);
+        }
+    }
+
+    #[test]
+    fn ring_extends_in_both_x_and_z() {
+        let pattern = ring(Color::white(), Color::black());
+        let examples = [
+            (point(0.0, 0.0, 0.0), Color::white()),
+            (point(1.0, 0.0, 0.0), Color::black()),
+            (point(0.0, 0.0, 1.0), Color::black()),
+            // 0.708 = just slightly more than sqrt(2)/2
+            (point(0.708, 0.0, 0.708), Color::black()),
+        ];
+        for (p, expected) in examples {
+            assert_eq!(pattern.color_at_point(&p), expected);
+        }
+    }
+
+    #[test]
+    fn checkers_repeat_in_every_direction() {
+        let pattern = checker(Color::white(), Color::black());
+        let examples = [
+            (point(0.0, 0.0, 0.0), Color::white()),
+            (point(0.99, 0.0, 0.0), Color::white()),
+            (point(1.01, 0.0, 0.0), Color::black()),
+            (point(0.0, 0.99, 0.0), Color::white()),
+            (point(0.0, 1.01, 0.0), Color::black()),
+            (point(0.0, 0.0, 0.99), Color::white()),
+            (point(0.0, 0.0, 1.01), Color::black()),
+            (point(-0.5, 0.0, 0.0), Color::black()),
+            (point(-0.5, -0.5, 0.0), Color::white()),
+        ];
+        for (p, expected) in examples {
+            assert_eq!(pattern.color_at_point(&p), expected);
+        }
+    }
+
+    #[test]
+    fn radial_gradient_interpolates_with_distance_from_y_axis() {
+        let pattern = radial_gradient(Color::white(), Color::black());
+        let examples = [
+            (point(0.0, 0.0, 0.0), Color::white()),
+            (point(0.5, 0.0, 0.0), Color::new(0.5, 0.5, 0.5)),
+            (point(0.0, 7.0, 0.25), Color::new(0.75, 0.75, 0.75)),
+            (point(0.6, 0.0, 0.8), Color::white()),
+            (point(1.2, 0.0, 1.6), Color::white()),
+        ];
+        for (p, expected) in examples {
+            assert_eq!(pattern.color_at_point(&p), expected);
+        }
+    }
+
+    #[test]
+    fn every_pattern_uses_its_own_transformation() {
+        let object = sphere();
+        for mut pattern in [
+            stripe(Color::white(), Color::black()),
+            gradient(Color::white(), Color::black()),
+            ring(Color::white(), Color::black()),
+            checker(Color::white(), Color::black()),
+            radial_gradient(Color::white(), Color::black()),
+        ] {
+            assert_eq!(
+                pattern.at(&object, &point(1.5, 0.0, 0.0)),
+                pattern.color_at_point(&point(1.5, 0.0, 0.0))
+            );
+            pattern.transformation = scaling(2.0, 2.0, 2.0);
+            assert_eq!(
+                pattern.at(&object, &point(1.5, 0.0, 0.0)),
+                pattern.color_at_point(&point(0.75, 0.0, 0.0))
+            );
+        }
+    }
+
+    #[test]
+    fn nested_patterns_use_the_inner_patterns_colors() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let green = Color::new(0.0, 1.0, 0.0);
+        let pattern = checker(stripe(Color::white(), Color::black()), stripe(red, green));
+        let examples = [
+            (point(0.5, 0.0, 0.0), Color::white()),
+            (point(0.5, 1.5, 0.0), red),
+            (point(1.5, 0.0, 0.0), green),
+            (point(1.5, 1.5, 0.0), Color::black()),
+        ];
+        for (p, expected) in examples {
+            assert_eq!(pattern.color_at_point(&p), expected);
+        }
+    }
+
+    #[test]
+    fn nested_patterns_keep_their_own_transformations() {
+        let object = sphere();
+        let mut inner = stripe(Color::white(), Color::black());
+        inner.transformation = scaling(0.5, 1.0, 1.0);
+        let mut outer = checker(inner, Color::new(1.0, 0.0, 0.0));
+        outer.transformation = scaling(2.0, 2.0, 2.0);
+        // Object x = 1.2 is checker x = 0.6, which is stripe x = 1.2.
+        assert_eq!(outer.at(&object, &point(1.2, 0.0, 0.0)), Color::black());
+        assert_eq!(outer.at(&object, &point(0.4, 0.0, 0.0)), Color::white());
+        assert_eq!(
+            outer.at(&object, &point(2.4, 0.0, 0.0)),
+            Color::new(1.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn blend_averages_two_patterns() {
+        let a = stripe(Color::white(), Color::black());
+        let mut b = stripe(Color::white(), Color::black());
+        b.transformation = rotation_y(PI / 2.0);
+        let pattern = blend(a, b);
+        let examples = [
+            (point(0.5, 0.0, -0.5), Color::white()),
+            (point(1.5, 0.0, -0.5), Color::new(0.5, 0.5, 0.5)),
+            (point(0.5, 0.0, 0.5), Color::new(0.5, 0.5, 0.5)),
+            (point(1.5, 0.0, 0.5), Color::black()),
+        ];
+        for (p, expected) in examples {
+            assert_eq!(pattern.color_at_point(&p), expected);
+        }
+    }
+
+    #[test]
+    fn perturbed_pattern_jitters_the_lookup_point() {
+        let pattern = perturbed(gradient(Color::white(), Color::black()), 0.2, 5);
+        let unperturbed = gradient(Color::white(), Color::black());
+        // Integer points move too, even though the noise is 0 there.
+        let p = point(1.0, 2.0, 3.0);
+        assert_ne!(pattern.color_at_point(&p), unperturbed.color_at_point(&p));
+
+        let p = point(0.3, 0.6, 0.2);
+        let c = pattern.color_at_point(&p);
+        assert_ne!(c, unperturbed.color_at_point(&p));
+        // Moved by no more than the scale.
+        let shade = 1.0 - c.red;
+        assert!((shade - 0.3).abs() <= 0.2);
+    }
+
+    #[test]
+    fn perturbed_pattern_moves_every_axis_and_depends_on_the_seed() {
+        let p = point(1.0, 2.0, 3.0);
+        let moved = perturbed(test_pattern(), 0.2, 5).color_at_point(&p);
+        for (jittered, original) in [(moved.red, 1.0), (moved.green, 2.0), (moved.blue, 3.0)] {
+            assert_ne!(jittered, original);
+            assert!((jittered - original).abs() <= 0.2);
+        }
+        assert_ne!(perturbed(test_pattern(), 0.2, 6).color_at_point(&p), moved);
+    }
+
+    #[test]
+    fn noise_patterns_blend_between_their_colors() {
+        let white = Color::white();
+        for pattern in [
+            clouds(white, Color::black(), 3),
+            marble(white, Color::black(), 3),
+            wood(white, Color::black(), 3),
+        ] {
+            let mut previous = None;
+            let mut varies = false;
+            for i in 0..200 {
+                let p = point(i as f64 * 0.173, i as f64 * 0.031, i as f64 * 0.097);
+                let c = pattern.color_at_point(&p);
+                assert!((0.0..=1.0).contains(&c.red));
+                // Grey, since a and b only differ in brightness.
+                assert_eq!(c.red, c.green);
+                assert_eq!(c.green, c.blue);
+                varies |= previous.is_some_and(|previous| previous != c);
+                previous = Some(c);
+            }
+            assert!(varies);
+        }
+    }
+
+    #[test]
+    fn noise_patterns_are_repeatable_for_a_seed() {
+        let p = point(0.35, 1.2, -0.8);
+        let a = marble(Color::white(), Color::black(), 11);
+        let b = marble(Color::white(), Color::black(), 11);
+        let c = marble(Color::white(), Color::black(), 12);
+        assert_eq!(a.color_at_point(&p), b.color_at_point(&p));
+        assert_ne!(a.color_at_point(&p), c.color_at_point(&p));
+    }
+
+    // A 2x2 image: red and green along the top, blue and white along the bottom.
+    fn two_by_two() -> Canvas {
+        let mut canvas = Canvas::new(2, 2);
+        canvas.write_pixel(0, 0, Color::new(1.0, 0.0, 0.0));
+        canvas.write_pixel(1, 0, Color::new(0.0, 1.0, 0.0));
+        canvas.write_pixel(0, 1, Color::new(0.0, 0.0, 1.0));
+        canvas.write_pixel(1, 1, Color::white());
+        canvas
+    }
+
+    #[test]
+    fn sampling_pixel_centres_gives_the_pixel_colors() {
+        let canvas = two_by_two();
+        let examples = [
+            (0.25, 0.75, Color::new(1.0, 0.0, 0.0)),
+            (0.75, 0.75, Color::new(0.0, 1.0, 0.0)),
+            (0.25, 0.25, Color::new(0.0, 0.0, 1.0)),
+            (0.75, 0.25, Color::white()),
+        ];
+        for wrap in [TextureWrap::Repeat, TextureWrap::Clamp] {
+            for (u, v, expected) in examples {
+                assert_eq!(sample_bilinear(&canvas, u, v, wrap), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn sampling_between_pixels_blends_them() {
+        let canvas = two_by_two();
+        let c = sample_bilinear(&canvas, 0.5, 0.75, TextureWrap::Clamp);
+        assert_eq!(c, Color::new(0.5, 0.5, 0.0));
+        let c = sample_bilinear(&canvas, 0.5, 0.5, TextureWrap::Clamp);
+        assert_eq!(c, Color::new(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn wrap_modes_differ_at_the_edges() {
+        let canvas = two_by_two();
+        // Left edge of the top row is halfway to the pixel on the far side when repeating.
+        let c = sample_bilinear(&canvas, 0.0, 0.75, TextureWrap::Repeat);
+        assert_eq!(c, Color::new(0.5, 0.5, 0.0));
+        let c = sample_bilinear(&canvas, 0.0, 0.75, TextureWrap::Clamp);
+        assert_eq!(c, Color::new(1.0, 0.0, 0.0));
+        // Outside the image entirely.
+        let c = sample_bilinear(&canvas, 1.25, 0.75, TextureWrap::Repeat);
+        assert_eq!(c, Color::new(1.0, 0.0, 0.0));
+        let c = sample_bilinear(&canvas, 1.25, 0.75, TextureWrap::Clamp);
+        assert_eq!(c, Color::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn texture_pattern_maps_the_image_onto_a_shape() {
+        let object = plane();
+        let pattern = texture(two_by_two(), UvMapping::Planar, TextureWrap::Repeat);
+        // Planar v runs along z.
+        assert_eq!(
+            pattern.at(&object, &point(0.25, 0.0, 0.75)),
+            Color::new(1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            pattern.at(&object, &point(1.75, 0.0, -0.75)),
+            Color::white()
+        );
+
+        let object = sphere();
+        let pattern = texture(two_by_two(), UvMapping::Spherical, TextureWrap::Clamp);
+        // The north pole is the middle of the top row of the image.
+        assert_eq!(
+            pattern.at(&object, &point(0.0, 1.0, 0.0)),
+            Color::new(0.5, 0.5, 0.0)
+        );
+    }
+
+    #[test]
+    fn clamped_texture_does_not_tile_across_a_plane() {
+        let object = plane();
+        let point_beyond = point(3.25, 0.0, 0.75);
+        let pattern = texture(two_by_two(), UvMapping::Planar, TextureWrap::Repeat);
+        assert_eq!(
+            pattern.at(&object, &point_beyond),
+            Color::new(1.0, 0.0, 0.0)
+        );
+        let pattern = texture(two_by_two(), UvMapping::Planar, TextureWrap::Clamp);
+        assert_eq!(
+            pattern.at(&object, &point_beyond),
+            Color::new(0.0, 1.0, 0.0)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one pixel")]
+    fn texture_from_an_empty_canvas_is_rejected() {
+        texture(Canvas::new(0, 0), UvMapping::Planar, TextureWrap::Repeat);
+    }
 }
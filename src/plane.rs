@@ -1,17 +1,16 @@
 use crate::{
+    bounds::BoundingBox,
     intersections::{intersection, Intersection},
-    matrix::{inverse, EPSILON},
-    rays::{transform, Ray},
+    matrix::EPSILON,
+    rays::Ray,
     shape::{Shape, ShapeType},
-    tuple::{vector, Tuple},
+    tuple::{point, vector, Tuple},
 };
 
 pub struct Plane {}
 
 impl Plane {
     pub fn local_intersect(s: &Shape, ray: &Ray) -> Vec<Intersection> {
-        let ray = transform(ray, &inverse(s.transform()));
-
         if ray.direction.y.abs() < EPSILON {
             return Vec::new();
         }
@@ -23,6 +22,19 @@ impl Plane {
     pub fn local_normal(_shape: &Shape, _pt: &Tuple) -> Tuple {
         vector(0.0, 1.0, 0.0)
     }
+
+    pub fn bounds(_shape: &Shape) -> BoundingBox {
+        BoundingBox::new(
+            point(f64::NEG_INFINITY, 0.0, f64::NEG_INFINITY),
+            point(f64::INFINITY, 0.0, f64::INFINITY),
+        )
+    }
+
+    // Planar mapping: one unit square of x and z covers the texture, and the texture's wrap
+    // mode decides what lies outside it.
+    pub fn uv_map(pt: &Tuple) -> (f64, f64) {
+        (pt.x, pt.z)
+    }
 }
 
 pub fn plane() -> Shape {
@@ -37,6 +49,8 @@ mod tests {
         tuple::{point, vector},
     };
 
+    use super::Plane;
+
     #[test]
     fn normal_of_plane_is_constant_everywhere() {
         let p = Shape::new(ShapeType::Plane);
@@ -50,6 +64,18 @@ mod tests {
         assert_eq!(n3, vector(0.0, 1.0, 0.0));
     }
 
+    #[test]
+    fn plane_has_an_unbounded_bounding_box() {
+        let p = Shape::new(ShapeType::Plane);
+        let b = p.bounds();
+        assert_eq!(b.min.x, f64::NEG_INFINITY);
+        assert_eq!(b.min.y, 0.0);
+        assert_eq!(b.min.z, f64::NEG_INFINITY);
+        assert_eq!(b.max.x, f64::INFINITY);
+        assert_eq!(b.max.y, 0.0);
+        assert_eq!(b.max.z, f64::INFINITY);
+    }
+
     #[test]
     fn intersect_with_ray_parallel() {
         let p = Shape::new(ShapeType::Plane);
@@ -93,4 +119,21 @@ mod tests {
         assert_eq!(xs[0].t, 1.0);
         assert_eq!(xs[0].object, p);
     }
+
+    #[test]
+    fn using_a_planar_mapping_on_a_3d_point() {
+        let examples = [
+            (point(0.25, 0.0, 0.5), 0.25, 0.5),
+            (point(0.25, 0.0, -0.25), 0.25, -0.25),
+            (point(0.25, 0.5, -0.25), 0.25, -0.25),
+            // Coordinates outside 0 to 1 are left for the texture to wrap.
+            (point(1.25, 0.0, 0.5), 1.25, 0.5),
+            (point(0.25, 0.0, -1.75), 0.25, -1.75),
+            (point(1.0, 0.0, -1.0), 1.0, -1.0),
+            (point(0.0, 0.0, 0.0), 0.0, 0.0),
+        ];
+        for (p, u, v) in examples {
+            assert_eq!(Plane::uv_map(&p), (u, v));
+        }
+    }
 }
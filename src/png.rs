@@ -0,0 +1,409 @@
+// A small PNG encoder: 8-bit RGB images, compressed with our own deflate so nothing
+// outside the standard library is needed.
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+// Deflate looks back at most this far for repeated bytes.
+const WINDOW_SIZE: usize = 32768;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const HASH_SIZE: usize = 1 << 15;
+
+// Base lengths and extra bits for length codes 257 to 285.
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+// Base distances and extra bits for distance codes 0 to 29.
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut c = n as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 == 1 {
+                0xedb8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    table
+}
+
+// The CRC-32 every PNG chunk ends with.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffff;
+    for &byte in data {
+        crc = CRC_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8);
+    }
+    crc ^ 0xffff_ffff
+}
+
+// The checksum zlib puts after the compressed data.
+pub fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 bytes is the most that can be summed before b could overflow.
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+// Writes bits least significant first, as deflate expects.
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    count: u32,
+}
+
+impl BitWriter {
+    fn new() -> BitWriter {
+        BitWriter {
+            bytes: vec![],
+            buffer: 0,
+            count: 0,
+        }
+    }
+
+    fn write_bits(&mut self, value: u32, bits: u32) {
+        self.buffer |= value << self.count;
+        self.count += bits;
+        while self.count >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    // Huffman codes are packed starting from their most significant bit.
+    fn write_code(&mut self, code: u32, bits: u32) {
+        let reversed = code.reverse_bits() >> (32 - bits);
+        self.write_bits(reversed, bits);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+// Writes a literal byte or length symbol using the fixed Huffman codes.
+fn write_literal_length(writer: &mut BitWriter, symbol: u32) {
+    match symbol {
+        0..=143 => writer.write_code(0x30 + symbol, 8),
+        144..=255 => writer.write_code(0x190 + symbol - 144, 9),
+        256..=279 => writer.write_code(symbol - 256, 7),
+        _ => writer.write_code(0xc0 + symbol - 280, 8),
+    }
+}
+
+fn write_match(writer: &mut BitWriter, length: usize, distance: usize) {
+    let code = LENGTH_BASE
+        .iter()
+        .rposition(|&b| b as usize <= length)
+        .unwrap();
+    write_literal_length(writer, 257 + code as u32);
+    writer.write_bits(
+        (length - LENGTH_BASE[code] as usize) as u32,
+        LENGTH_EXTRA[code] as u32,
+    );
+
+    let code = DISTANCE_BASE
+        .iter()
+        .rposition(|&b| b as usize <= distance)
+        .unwrap();
+    writer.write_code(code as u32, 5);
+    writer.write_bits(
+        (distance - DISTANCE_BASE[code] as usize) as u32,
+        DISTANCE_EXTRA[code] as u32,
+    );
+}
+
+fn hash(data: &[u8]) -> usize {
+    let v = (data[0] as usize) << 16 | (data[1] as usize) << 8 | data[2] as usize;
+    (v.wrapping_mul(2_654_435_761) >> 8) % HASH_SIZE
+}
+
+// Compresses into a single deflate block using the fixed Huffman codes. Repeats are found
+// by remembering only the last place each three bytes were seen, which is quick and does
+// well on the long runs of the same color that renders are full of.
+pub fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::new();
+    // Final block, fixed Huffman codes.
+    writer.write_bits(1, 1);
+    writer.write_bits(1, 2);
+
+    let mut last_seen = vec![usize::MAX; HASH_SIZE];
+    let mut i = 0;
+    while i < data.len() {
+        let mut length = 0;
+        let mut distance = 0;
+        if i + MIN_MATCH <= data.len() {
+            let h = hash(&data[i..]);
+            let candidate = last_seen[h];
+            last_seen[h] = i;
+            if candidate != usize::MAX && i - candidate <= WINDOW_SIZE {
+                let max = (data.len() - i).min(MAX_MATCH);
+                while length < max && data[candidate + length] == data[i + length] {
+                    length += 1;
+                }
+                distance = i - candidate;
+            }
+        }
+
+        if length >= MIN_MATCH {
+            write_match(&mut writer, length, distance);
+            // Remember the skipped positions too, so later repeats can find them.
+            for j in i + 1..(i + length).min(data.len().saturating_sub(MIN_MATCH - 1)) {
+                last_seen[hash(&data[j..])] = j;
+            }
+            i += length;
+        } else {
+            write_literal_length(&mut writer, data[i] as u32);
+            i += 1;
+        }
+    }
+
+    write_literal_length(&mut writer, 256);
+    writer.finish()
+}
+
+// Deflate data wrapped with the zlib header and checksum, as PNG stores it.
+pub fn zlib_compress(data: &[u8]) -> Vec<u8> {
+    // 32K window, deflate, no dictionary; the check bits make the header a multiple of 31.
+    let mut result = vec![0x78, 0x01];
+    result.extend(deflate(data));
+    result.extend(adler32(data).to_be_bytes());
+    result
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend(kind);
+    png.extend(data);
+    let crc = crc32(&png[start..]);
+    png.extend(crc.to_be_bytes());
+}
+
+// Encodes 8-bit RGB pixels, three bytes each and row by row, as a PNG file.
+pub fn encode(width: usize, height: usize, rgb: &[u8]) -> Vec<u8> {
+    assert_eq!(rgb.len(), width * height * 3, "wrong number of pixel bytes");
+
+    let mut header = vec![];
+    header.extend((width as u32).to_be_bytes());
+    header.extend((height as u32).to_be_bytes());
+    // 8 bits per channel, RGB, deflate, standard filters, not interlaced.
+    header.extend([8, 2, 0, 0, 0]);
+
+    // Each row starts with its filter type; 0 leaves the row as it is.
+    let mut scanlines = Vec::with_capacity((width * 3 + 1) * height);
+    let stride = width * 3;
+    for y in 0..height {
+        scanlines.push(0);
+        scanlines.extend(&rgb[y * stride..(y + 1) * stride]);
+    }
+
+    let mut png = SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &zlib_compress(&scanlines));
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Just enough of an inflater to check our output: stored and fixed Huffman blocks.
+    struct BitReader<'a> {
+        data: &'a [u8],
+        position: usize,
+    }
+
+    impl BitReader<'_> {
+        fn bit(&mut self) -> u32 {
+            let bit = (self.data[self.position / 8] >> (self.position % 8)) & 1;
+            self.position += 1;
+            bit as u32
+        }
+
+        fn bits(&mut self, count: u32) -> u32 {
+            (0..count).fold(0, |value, i| value | self.bit() << i)
+        }
+
+        fn fixed_literal_length(&mut self) -> u32 {
+            let mut code = 0;
+            for length in 1..=9 {
+                code = code << 1 | self.bit();
+                match (length, code) {
+                    (7, 0..=0x17) => return 256 + code,
+                    (8, 0x30..=0xbf) => return code - 0x30,
+                    (8, 0xc0..=0xc7) => return 280 + code - 0xc0,
+                    (9, 0x190..=0x1ff) => return 144 + code - 0x190,
+                    _ => {}
+                }
+            }
+            panic!("invalid fixed Huffman code");
+        }
+    }
+
+    fn inflate(data: &[u8]) -> Vec<u8> {
+        let mut reader = BitReader { data, position: 0 };
+        let mut out: Vec<u8> = vec![];
+        loop {
+            let last = reader.bit() == 1;
+            match reader.bits(2) {
+                0 => {
+                    reader.position = reader.position.div_ceil(8) * 8;
+                    let length = reader.bits(16) as usize;
+                    let _ones_complement = reader.bits(16);
+                    let start = reader.position / 8;
+                    out.extend(&data[start..start + length]);
+                    reader.position += length * 8;
+                }
+                1 => loop {
+                    let symbol = reader.fixed_literal_length();
+                    if symbol < 256 {
+                        out.push(symbol as u8);
+                        continue;
+                    }
+                    if symbol == 256 {
+                        break;
+                    }
+                    let code = (symbol - 257) as usize;
+                    let length = LENGTH_BASE[code] as usize
+                        + reader.bits(LENGTH_EXTRA[code] as u32) as usize;
+                    let mut code = 0;
+                    for _ in 0..5 {
+                        code = code << 1 | reader.bit();
+                    }
+                    let code = code as usize;
+                    let distance = DISTANCE_BASE[code] as usize
+                        + reader.bits(DISTANCE_EXTRA[code] as u32) as usize;
+                    for _ in 0..length {
+                        out.push(out[out.len() - distance]);
+                    }
+                },
+                kind => panic!("unsupported block type {}", kind),
+            }
+            if last {
+                return out;
+            }
+        }
+    }
+
+    fn zlib_decompress(data: &[u8]) -> Vec<u8> {
+        assert_eq!((data[0] as u16 * 256 + data[1] as u16) % 31, 0);
+        assert_eq!(data[0] & 0x0f, 8);
+        let out = inflate(&data[2..data.len() - 4]);
+        let checksum = u32::from_be_bytes(data[data.len() - 4..].try_into().unwrap());
+        assert_eq!(checksum, adler32(&out));
+        out
+    }
+
+    // Splits a PNG into its chunks, checking each CRC on the way.
+    fn chunks(png: &[u8]) -> Vec<(String, Vec<u8>)> {
+        assert_eq!(png[..8], SIGNATURE);
+        let mut result = vec![];
+        let mut rest = &png[8..];
+        while !rest.is_empty() {
+            let length = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+            let kind = &rest[4..8];
+            let data = &rest[8..8 + length];
+            let crc = u32::from_be_bytes(rest[8 + length..12 + length].try_into().unwrap());
+            assert_eq!(crc, crc32(&rest[4..8 + length]));
+            result.push((String::from_utf8(kind.to_vec()).unwrap(), data.to_vec()));
+            rest = &rest[12 + length..];
+        }
+        result
+    }
+
+    #[test]
+    fn crc32_of_known_strings() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+    }
+
+    #[test]
+    fn adler32_of_known_strings() {
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn inflating_a_stored_block() {
+        // Final stored block holding "abc".
+        let data = [0x01, 0x03, 0x00, 0xfc, 0xff, b'a', b'b', b'c'];
+        assert_eq!(inflate(&data), b"abc");
+    }
+
+    #[test]
+    fn deflate_round_trip() {
+        let mut data = b"aaaaaaaaaabcabcabcabc hello hello hello".to_vec();
+        // Bytes that never repeat, then a long run, then a repeat from far back.
+        data.extend((0..=255u8).rev());
+        data.extend([7; 1000]);
+        data.extend(b"aaaaaaaaaabcabcabcabc");
+        assert_eq!(inflate(&deflate(&data)), data);
+        assert_eq!(inflate(&deflate(&[])), b"");
+    }
+
+    #[test]
+    fn deflate_shrinks_repetitive_data() {
+        let data = vec![42; 10000];
+        assert!(deflate(&data).len() < 100);
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let (width, height) = (5, 3);
+        let rgb: Vec<u8> = (0..width * height * 3)
+            .map(|i| (i * 17 % 256) as u8)
+            .collect();
+        let png = encode(width, height, &rgb);
+
+        let chunks = chunks(&png);
+        let kinds: Vec<&str> = chunks.iter().map(|(kind, _)| kind.as_str()).collect();
+        assert_eq!(kinds, ["IHDR", "IDAT", "IEND"]);
+        assert_eq!(
+            chunks[0].1,
+            [0, 0, 0, 5, 0, 0, 0, 3, 8, 2, 0, 0, 0].to_vec()
+        );
+
+        let scanlines = zlib_decompress(&chunks[1].1);
+        assert_eq!(scanlines.len(), (width * 3 + 1) * height);
+        for (y, row) in scanlines.chunks(width * 3 + 1).enumerate() {
+            assert_eq!(row[0], 0);
+            assert_eq!(&row[1..], &rgb[y * width * 3..(y + 1) * width * 3]);
+        }
+    }
+}
@@ -1,19 +1,53 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
 use crate::{
+    bounds::BoundingBox,
+    bvh::Bvh,
+    cone::Cone,
+    csg::{Csg, CsgOperation},
+    cube::Cube,
+    cylinder::Cylinder,
+    group::Group,
     intersections::Intersection,
     materials::Material,
     matrix::{inverse, Matrix},
     plane::Plane,
     rays::{transform, Ray},
     sphere::Sphere,
+    triangle::{SmoothTriangle, Triangle},
     tuple::{normalise, Tuple},
 };
 
-static mut ID_TRACKER: u64 = 0;
+// Atomic so shapes can be created on any thread.
+static ID_TRACKER: AtomicU64 = AtomicU64::new(1);
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum ShapeType {
+    Cone {
+        minimum: f64,
+        maximum: f64,
+        closed: bool,
+    },
+    Csg {
+        operation: CsgOperation,
+        left: Box<Shape>,
+        right: Box<Shape>,
+    },
+    Cube,
+    Cylinder {
+        minimum: f64,
+        maximum: f64,
+        closed: bool,
+    },
+    Group {
+        children: Vec<Shape>,
+        // Built on demand by build_bvh, and thrown away when the children change.
+        bvh: Option<Bvh>,
+    },
     Plane,
+    SmoothTriangle(SmoothTriangle),
     Sphere,
+    Triangle(Triangle),
 }
 
 #[derive(Clone, Debug)]
@@ -22,6 +56,8 @@ pub struct Shape {
     pub shape_type: ShapeType,
     transform: Matrix,
     material: Material,
+    // Transforms of the groups containing this shape, outermost first.
+    parents: Vec<Matrix>,
 }
 
 impl PartialEq for Shape {
@@ -32,16 +68,17 @@ impl PartialEq for Shape {
 
 impl Shape {
     pub fn new(shape_type: ShapeType) -> Shape {
-        let id = unsafe {
-            ID_TRACKER += 1;
-            ID_TRACKER
-        };
-        Shape {
+        let id = ID_TRACKER.fetch_add(1, Ordering::Relaxed);
+        let mut shape = Shape {
             id,
             shape_type,
             transform: Matrix::identity_4x4(),
             material: Material::default(),
-        }
+            parents: vec![],
+        };
+        // Any children passed in need to know about their new parent.
+        shape.set_parents(vec![]);
+        shape
     }
 
     pub fn id(&self) -> u64 {
@@ -62,6 +99,97 @@ impl Shape {
 
     pub fn set_transform(&mut self, transformation: Matrix) {
         self.transform = transformation;
+        // Children inherit the new transform.
+        self.set_parents(self.parents.clone());
+    }
+
+    pub fn parents(&self) -> &[Matrix] {
+        &self.parents
+    }
+
+    pub fn children(&self) -> &[Shape] {
+        match &self.shape_type {
+            ShapeType::Group { children, .. } => children,
+            _ => &[],
+        }
+    }
+
+    pub fn add_child(&mut self, mut child: Shape) {
+        let mut chain = self.parents.clone();
+        chain.push(self.transform.clone());
+        child.set_parents(chain);
+
+        let ShapeType::Group { children, bvh } = &mut self.shape_type else {
+            panic!("Only groups can have children");
+        };
+        children.push(child);
+        *bvh = None;
+    }
+
+    // Builds bounding volume hierarchies for this group and any groups nested inside it,
+    // so intersecting rays can skip children they can't possibly hit.
+    pub fn build_bvh(&mut self) {
+        match &mut self.shape_type {
+            ShapeType::Group { children, bvh } => {
+                for child in children.iter_mut() {
+                    child.build_bvh();
+                }
+                *bvh = Some(Bvh::build(children));
+            }
+            ShapeType::Csg { left, right, .. } => {
+                left.build_bvh();
+                right.build_bvh();
+            }
+            _ => {}
+        }
+    }
+
+    fn set_parents(&mut self, parents: Vec<Matrix>) {
+        self.parents = parents;
+
+        let mut chain = self.parents.clone();
+        chain.push(self.transform.clone());
+        match &mut self.shape_type {
+            ShapeType::Group { children, .. } => {
+                for child in children {
+                    child.set_parents(chain.clone());
+                }
+            }
+            ShapeType::Csg { left, right, .. } => {
+                left.set_parents(chain.clone());
+                right.set_parents(chain);
+            }
+            _ => {}
+        }
+    }
+
+    // Whether other is this shape, or is contained somewhere inside it.
+    pub fn includes(&self, other: &Shape) -> bool {
+        match &self.shape_type {
+            ShapeType::Group { children, .. } => children.iter().any(|c| c.includes(other)),
+            ShapeType::Csg { left, right, .. } => left.includes(other) || right.includes(other),
+            _ => self == other,
+        }
+    }
+
+    // Converts a point from world space to object space, taking any parent groups into account.
+    pub fn world_to_object(&self, point: &Tuple) -> Tuple {
+        let mut point = point.clone();
+        for parent in &self.parents {
+            point = inverse(parent) * point;
+        }
+        inverse(self.transform()) * point
+    }
+
+    // Converts a normal from object space to world space, taking any parent groups into account.
+    pub fn normal_to_world(&self, normal: &Tuple) -> Tuple {
+        let mut normal = normal.clone();
+        for m in std::iter::once(&self.transform).chain(self.parents.iter().rev()) {
+            normal = inverse(m).transpose() * normal;
+            normal.w = 0.0;
+            normal = normalise(&normal);
+        }
+        normal
     }
 
     pub fn intersect(&self, ray: &Ray) -> Vec<Intersection> {
@@ -72,23 +200,67 @@ impl Shape {
         match self.shape_type {
             ShapeType::Sphere => Sphere::local_intersect(self, &ray),
             ShapeType::Plane => Plane::local_intersect(self, &ray),
+            ShapeType::Cube => Cube::local_intersect(self, &ray),
+            ShapeType::Group { .. } => Group::local_intersect(self, &ray),
+            ShapeType::Csg { .. } => Csg::local_intersect(self, &ray),
+            ShapeType::Cylinder { .. } => Cylinder::local_intersect(self, &ray),
+            ShapeType::Cone { .. } => Cone::local_intersect(self, &ray),
+            ShapeType::Triangle(_) => Triangle::local_intersect(self, &ray),
+            ShapeType::SmoothTriangle(_) => SmoothTriangle::local_intersect(self, &ray),
         }
     }
 
+    // The extent of the shape in its own object space.
+    pub fn bounds(&self) -> BoundingBox {
+        match self.shape_type {
+            ShapeType::Sphere => Sphere::bounds(self),
+            ShapeType::Plane => Plane::bounds(self),
+            ShapeType::Cube => Cube::bounds(self),
+            ShapeType::Group { .. } => Group::bounds(self),
+            ShapeType::Csg { .. } => Csg::bounds(self),
+            ShapeType::Cylinder { .. } => Cylinder::bounds(self),
+            ShapeType::Cone { .. } => Cone::bounds(self),
+            ShapeType::Triangle(_) => Triangle::bounds(self),
+            ShapeType::SmoothTriangle(_) => SmoothTriangle::bounds(self),
+        }
+    }
+
+    // The extent of the shape once its transform has been applied, i.e. in the space of
+    // whatever contains it.
+    pub fn parent_space_bounds(&self) -> BoundingBox {
+        self.bounds().transform(self.transform())
+    }
+
     pub fn normal_at(&self, point: &Tuple) -> Tuple {
+        self.normal_at_point(point, None)
+    }
+
+    // Like normal_at, but smooth triangles use the hit's u and v to interpolate their normal.
+    pub fn normal_at_hit(&self, point: &Tuple, hit: &Intersection) -> Tuple {
+        self.normal_at_point(point, Some(hit))
+    }
+
+    fn normal_at_point(&self, point: &Tuple, hit: Option<&Intersection>) -> Tuple {
         // Convert the point to object space.
-        let obj_point = inverse(self.transform()) * point;
+        let obj_point = self.world_to_object(point);
 
         // Calculate the normal in object space.
         let local_normal = match self.shape_type {
             ShapeType::Sphere => Sphere::local_normal(self, &obj_point),
             ShapeType::Plane => Plane::local_normal(self, &obj_point),
+            ShapeType::Cube => Cube::local_normal(self, &obj_point),
+            ShapeType::Group { .. } => panic!("Groups don't have normals, only their children do"),
+            ShapeType::Csg { .. } => {
+                panic!("CSG shapes don't have normals, only their children do")
+            }
+            ShapeType::Cylinder { .. } => Cylinder::local_normal(self, &obj_point),
+            ShapeType::Cone { .. } => Cone::local_normal(self, &obj_point),
+            ShapeType::Triangle(_) => Triangle::local_normal(self, &obj_point),
+            ShapeType::SmoothTriangle(_) => SmoothTriangle::local_normal(self, &obj_point, hit),
         };
 
         // Transform back to world space.
-        let mut world_normal = inverse(self.transform()).transpose() * local_normal;
-        world_normal.w = 0.0;
-        normalise(&world_normal)
+        self.normal_to_world(&local_normal)
     }
 }
 
@@ -1,8 +1,12 @@
+use std::f64::consts::PI;
+
 use crate::{
+    bounds::BoundingBox,
     intersections::{intersection, Intersection},
+    materials::Material,
     rays::Ray,
     shape::{Shape, ShapeType},
-    tuple::{dot, point, Tuple},
+    tuple::{dot, magnitude, point, vector, Tuple},
 };
 
 #[derive(Clone, Debug)]
@@ -28,17 +32,44 @@ impl Sphere {
     pub fn local_normal(_shape: &Shape, pt: &Tuple) -> Tuple {
         pt - point(0.0, 0.0, 0.0)
     }
+
+    pub fn bounds(_shape: &Shape) -> BoundingBox {
+        BoundingBox::new(point(-1.0, -1.0, -1.0), point(1.0, 1.0, 1.0))
+    }
+
+    // Spherical mapping: u goes around the equator and v from the south to north pole,
+    // both from 0.0 to 1.0.
+    pub fn uv_map(pt: &Tuple) -> (f64, f64) {
+        // Azimuthal angle, from -pi to pi.
+        let theta = pt.x.atan2(pt.z);
+        let radius = magnitude(&vector(pt.x, pt.y, pt.z));
+        // Polar angle, from 0 to pi.
+        let phi = (pt.y / radius).acos();
+
+        // Flip u so it increases counter-clockwise when viewed from above.
+        let raw_u = theta / (2.0 * PI);
+        let u = 1.0 - (raw_u + 0.5);
+        let v = 1.0 - phi / PI;
+        (u, v)
+    }
 }
 
 pub fn sphere() -> Shape {
     Shape::new(ShapeType::Sphere)
 }
 
+pub fn glass_sphere() -> Shape {
+    let mut s = sphere();
+    s.set_material(Material::default().transparency(1.0).refractive_index(1.5));
+    s
+}
+
 #[cfg(test)]
 mod tests {
-    use std::f64::consts::PI;
+    use std::f64::consts::{FRAC_1_SQRT_2, PI};
 
     use crate::{
+        matrix::{Matrix, EPSILON},
         rays::ray,
         transformations::{rotation_z, scaling, translation},
         tuple::{normalise, point, vector},
@@ -59,6 +90,31 @@ mod tests {
         assert_eq!(s1, s1);
     }
 
+    #[test]
+    fn glass_sphere_is_transparent() {
+        let s = glass_sphere();
+        assert_eq!(s.transform(), &Matrix::identity_4x4());
+        assert_eq!(s.material().transparency, 1.0);
+        assert_eq!(s.material().refractive_index, 1.5);
+    }
+
+    #[test]
+    fn sphere_has_a_bounding_box() {
+        let s = sphere();
+        let b = s.bounds();
+        assert_eq!(b.min, point(-1.0, -1.0, -1.0));
+        assert_eq!(b.max, point(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn querying_bounding_box_in_parent_space() {
+        let mut s = sphere();
+        s.set_transform(translation(1.0, -3.0, 5.0) * scaling(0.5, 2.0, 4.0));
+        let b = s.parent_space_bounds();
+        assert_eq!(b.min, point(0.5, -5.0, 1.0));
+        assert_eq!(b.max, point(1.5, -1.0, 9.0));
+    }
+
     #[test]
     fn intersect_set_the_object() {
         let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
@@ -135,9 +191,9 @@ mod tests {
         let mut s = Shape::new(ShapeType::Sphere);
         s.set_transform(translation(0.0, 1.0, 0.0));
 
-        let n = Shape::normal_at(&s, &point(0.0, 1.70711, -0.70711));
+        let n = Shape::normal_at(&s, &point(0.0, 1.0 + FRAC_1_SQRT_2, -FRAC_1_SQRT_2));
 
-        assert_eq!(n, vector(0.0, 0.70711, -0.70711));
+        assert_eq!(n, vector(0.0, FRAC_1_SQRT_2, -FRAC_1_SQRT_2));
     }
 
     #[test]
@@ -150,4 +206,22 @@ mod tests {
 
         assert_eq!(n, vector(0.0, 0.97014, -0.24254));
     }
+
+    #[test]
+    fn using_a_spherical_mapping_on_a_3d_point() {
+        let examples = [
+            (point(0.0, 0.0, -1.0), 0.0, 0.5),
+            (point(1.0, 0.0, 0.0), 0.25, 0.5),
+            (point(0.0, 0.0, 1.0), 0.5, 0.5),
+            (point(-1.0, 0.0, 0.0), 0.75, 0.5),
+            (point(0.0, 1.0, 0.0), 0.5, 1.0),
+            (point(0.0, -1.0, 0.0), 0.5, 0.0),
+            (point(FRAC_1_SQRT_2, FRAC_1_SQRT_2, 0.0), 0.25, 0.75),
+        ];
+        for (p, u, v) in examples {
+            let (actual_u, actual_v) = Sphere::uv_map(&p);
+            assert!((actual_u - u).abs() < EPSILON);
+            assert!((actual_v - v).abs() < EPSILON);
+        }
+    }
 }
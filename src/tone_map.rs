@@ -0,0 +1,177 @@
+// Turning the linear light values the renderer works in into colors for a display.
+// Only 8-bit outputs like PPM and PNG need this; float formats keep the raw values.
+
+use crate::color::Color;
+
+// How colors brighter than 1.0 are squeezed into the displayable range.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ToneMapOperator {
+    // Anything over 1.0 is clipped.
+    Clamp,
+    // c / (1 + c): never quite reaches white, and darkens the midtones a little.
+    Reinhard,
+    // Narkowicz's fit of the ACES filmic curve, which rolls highlights off gently and adds
+    // some contrast.
+    Aces,
+}
+
+// How the tone mapped value is encoded for the display.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Transfer {
+    Linear,
+    // The standard curve nearly every monitor and image viewer expects.
+    Srgb,
+    // A plain power curve: value ^ (1 / gamma).
+    Gamma(f64),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ToneMap {
+    // In stops: each +1.0 doubles the brightness.
+    pub exposure: f64,
+    pub operator: ToneMapOperator,
+    pub transfer: Transfer,
+}
+
+impl Default for ToneMap {
+    // Leaves colors alone, so they are only clamped when written.
+    fn default() -> Self {
+        ToneMap {
+            exposure: 0.0,
+            operator: ToneMapOperator::Clamp,
+            transfer: Transfer::Linear,
+        }
+    }
+}
+
+impl ToneMap {
+    pub fn exposure(mut self, stops: f64) -> Self {
+        self.exposure = stops;
+        self
+    }
+
+    pub fn operator(mut self, operator: ToneMapOperator) -> Self {
+        self.operator = operator;
+        self
+    }
+
+    pub fn transfer(mut self, transfer: Transfer) -> Self {
+        if let Transfer::Gamma(gamma) = transfer {
+            assert!(
+                gamma > 0.0 && gamma.is_finite(),
+                "gamma must be positive and finite, not {}",
+                gamma
+            );
+        }
+        self.transfer = transfer;
+        self
+    }
+
+    pub fn apply(&self, color: &Color) -> Color {
+        let scale = 2f64.powf(self.exposure);
+        let map = |value: f64| {
+            // Negative light doesn't exist, but can creep in from filtering or bad inputs.
+            let value = (value * scale).max(0.0);
+            let mapped = match self.operator {
+                ToneMapOperator::Clamp => value.min(1.0),
+                ToneMapOperator::Reinhard => value / (1.0 + value),
+                ToneMapOperator::Aces => {
+                    let curve =
+                        (value * (2.51 * value + 0.03)) / (value * (2.43 * value + 0.59) + 0.14);
+                    curve.clamp(0.0, 1.0)
+                }
+            };
+            match self.transfer {
+                Transfer::Linear => mapped,
+                Transfer::Srgb => {
+                    if mapped <= 0.0031308 {
+                        mapped * 12.92
+                    } else {
+                        1.055 * mapped.powf(1.0 / 2.4) - 0.055
+                    }
+                }
+                Transfer::Gamma(gamma) => mapped.powf(1.0 / gamma),
+            }
+        };
+        Color::new(map(color.red), map(color.green), map(color.blue))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_tone_map_changes_nothing_in_range() {
+        let c = Color::new(0.2, 0.5, 1.0);
+        assert_eq!(ToneMap::default().apply(&c), c);
+    }
+
+    #[test]
+    fn clamp_clips_bright_and_negative_values() {
+        let c = Color::new(-0.5, 0.5, 3.0);
+        assert_eq!(ToneMap::default().apply(&c), Color::new(0.0, 0.5, 1.0));
+    }
+
+    #[test]
+    fn exposure_is_in_stops() {
+        let c = Color::new(0.1, 0.2, 0.4);
+        assert_eq!(
+            ToneMap::default().exposure(1.0).apply(&c),
+            Color::new(0.2, 0.4, 0.8)
+        );
+        assert_eq!(
+            ToneMap::default().exposure(-2.0).apply(&c),
+            Color::new(0.025, 0.05, 0.1)
+        );
+    }
+
+    #[test]
+    fn reinhard_maps_bright_values_below_one() {
+        let t = ToneMap::default().operator(ToneMapOperator::Reinhard);
+        assert_eq!(
+            t.apply(&Color::new(0.0, 1.0, 3.0)),
+            Color::new(0.0, 0.5, 0.75)
+        );
+        assert!(t.apply(&Color::new(1000.0, 0.0, 0.0)).red < 1.0);
+    }
+
+    #[test]
+    fn aces_is_increasing_and_saturates() {
+        let t = ToneMap::default().operator(ToneMapOperator::Aces);
+        assert_eq!(
+            t.apply(&Color::new(0.0, 0.0, 0.0)),
+            Color::new(0.0, 0.0, 0.0)
+        );
+        let mut previous = 0.0;
+        for i in 1..100 {
+            let v = t.apply(&Color::new(i as f64 * 0.2, 0.0, 0.0)).red;
+            assert!(v >= previous && v <= 1.0);
+            previous = v;
+        }
+        assert_eq!(t.apply(&Color::new(100.0, 0.0, 0.0)).red, 1.0);
+    }
+
+    #[test]
+    fn srgb_transfer() {
+        let t = ToneMap::default().transfer(Transfer::Srgb);
+        let c = t.apply(&Color::new(0.0, 0.002, 0.5));
+        assert_eq!(c, Color::new(0.0, 0.02584, 0.73536));
+        assert_eq!(t.apply(&Color::white()), Color::white());
+    }
+
+    #[test]
+    fn gamma_transfer() {
+        let t = ToneMap::default().transfer(Transfer::Gamma(2.0));
+        assert_eq!(
+            t.apply(&Color::new(0.25, 0.04, 1.0)),
+            Color::new(0.5, 0.2, 1.0)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "gamma must be positive")]
+    fn zero_gamma_is_rejected() {
+        ToneMap::default().transfer(Transfer::Gamma(0.0));
+    }
+}
@@ -0,0 +1,292 @@
+use crate::{
+    bounds::BoundingBox,
+    intersections::{intersection_with_uv, Intersection},
+    matrix::EPSILON,
+    rays::Ray,
+    shape::{Shape, ShapeType},
+    tuple::{cross, dot, normalise, Tuple},
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Triangle {
+    pub p1: Tuple,
+    pub p2: Tuple,
+    pub p3: Tuple,
+    pub e1: Tuple,
+    pub e2: Tuple,
+    pub normal: Tuple,
+}
+
+impl Triangle {
+    pub fn new(p1: Tuple, p2: Tuple, p3: Tuple) -> Triangle {
+        let e1 = &p2 - &p1;
+        let e2 = &p3 - &p1;
+        let normal = normalise(&cross(&e2, &e1));
+        Triangle {
+            p1,
+            p2,
+            p3,
+            e1,
+            e2,
+            normal,
+        }
+    }
+
+    pub fn local_intersect(s: &Shape, ray: &Ray) -> Vec<Intersection> {
+        let ShapeType::Triangle(triangle) = &s.shape_type else {
+            unreachable!("not a triangle")
+        };
+
+        moller_trumbore(s, ray, &triangle.p1, &triangle.e1, &triangle.e2)
+    }
+
+    pub fn local_normal(shape: &Shape, _pt: &Tuple) -> Tuple {
+        let ShapeType::Triangle(triangle) = &shape.shape_type else {
+            unreachable!("not a triangle")
+        };
+
+        triangle.normal.clone()
+    }
+
+    pub fn bounds(shape: &Shape) -> BoundingBox {
+        let ShapeType::Triangle(triangle) = &shape.shape_type else {
+            unreachable!("not a triangle")
+        };
+
+        bounds_of_corners(&triangle.p1, &triangle.p2, &triangle.p3)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SmoothTriangle {
+    pub p1: Tuple,
+    pub p2: Tuple,
+    pub p3: Tuple,
+    pub n1: Tuple,
+    pub n2: Tuple,
+    pub n3: Tuple,
+    pub e1: Tuple,
+    pub e2: Tuple,
+}
+
+impl SmoothTriangle {
+    pub fn new(p1: Tuple, p2: Tuple, p3: Tuple, n1: Tuple, n2: Tuple, n3: Tuple) -> SmoothTriangle {
+        let e1 = &p2 - &p1;
+        let e2 = &p3 - &p1;
+        SmoothTriangle {
+            p1,
+            p2,
+            p3,
+            n1,
+            n2,
+            n3,
+            e1,
+            e2,
+        }
+    }
+
+    pub fn local_intersect(s: &Shape, ray: &Ray) -> Vec<Intersection> {
+        let ShapeType::SmoothTriangle(triangle) = &s.shape_type else {
+            unreachable!("not a smooth triangle")
+        };
+
+        moller_trumbore(s, ray, &triangle.p1, &triangle.e1, &triangle.e2)
+    }
+
+    pub fn local_normal(shape: &Shape, _pt: &Tuple, hit: Option<&Intersection>) -> Tuple {
+        let ShapeType::SmoothTriangle(triangle) = &shape.shape_type else {
+            unreachable!("not a smooth triangle")
+        };
+
+        // Without a hit there is nothing to interpolate with, so use the face normal.
+        let Some(hit) = hit else {
+            return normalise(&cross(&triangle.e2, &triangle.e1));
+        };
+
+        // Blend the vertex normals using the barycentric coordinates of the hit.
+        &(&(&triangle.n2 * hit.u) + &(&triangle.n3 * hit.v))
+            + &(&triangle.n1 * (1.0 - hit.u - hit.v))
+    }
+
+    pub fn bounds(shape: &Shape) -> BoundingBox {
+        let ShapeType::SmoothTriangle(triangle) = &shape.shape_type else {
+            unreachable!("not a smooth triangle")
+        };
+
+        bounds_of_corners(&triangle.p1, &triangle.p2, &triangle.p3)
+    }
+}
+
+// Möller–Trumbore ray/triangle intersection.
+// Also records where on the triangle the hit was, relative to the corners, as u and v.
+fn moller_trumbore(s: &Shape, ray: &Ray, p1: &Tuple, e1: &Tuple, e2: &Tuple) -> Vec<Intersection> {
+    let dir_cross_e2 = cross(&ray.direction, e2);
+    let det = dot(e1, &dir_cross_e2);
+    if det.abs() < EPSILON {
+        // Ray is parallel to the triangle.
+        return vec![];
+    }
+
+    let f = 1.0 / det;
+    let p1_to_origin = &ray.origin - p1;
+    let u = f * dot(&p1_to_origin, &dir_cross_e2);
+    if !(0.0..=1.0).contains(&u) {
+        return vec![];
+    }
+
+    let origin_cross_e1 = cross(&p1_to_origin, e1);
+    let v = f * dot(&ray.direction, &origin_cross_e1);
+    if v < 0.0 || (u + v) > 1.0 {
+        return vec![];
+    }
+
+    let t = f * dot(e2, &origin_cross_e1);
+    vec![intersection_with_uv(t, s, u, v)]
+}
+
+fn bounds_of_corners(p1: &Tuple, p2: &Tuple, p3: &Tuple) -> BoundingBox {
+    let mut result = BoundingBox::empty();
+    result.add_point(p1);
+    result.add_point(p2);
+    result.add_point(p3);
+    result
+}
+
+pub fn triangle(p1: Tuple, p2: Tuple, p3: Tuple) -> Shape {
+    Shape::new(ShapeType::Triangle(Triangle::new(p1, p2, p3)))
+}
+
+pub fn smooth_triangle(p1: Tuple, p2: Tuple, p3: Tuple, n1: Tuple, n2: Tuple, n3: Tuple) -> Shape {
+    Shape::new(ShapeType::SmoothTriangle(SmoothTriangle::new(
+        p1, p2, p3, n1, n2, n3,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        intersections::prepare_computations,
+        rays::ray,
+        tuple::{point, vector},
+    };
+
+    use super::*;
+
+    fn default_triangle() -> Shape {
+        triangle(
+            point(0.0, 1.0, 0.0),
+            point(-1.0, 0.0, 0.0),
+            point(1.0, 0.0, 0.0),
+        )
+    }
+
+    fn default_smooth_triangle() -> Shape {
+        smooth_triangle(
+            point(0.0, 1.0, 0.0),
+            point(-1.0, 0.0, 0.0),
+            point(1.0, 0.0, 0.0),
+            vector(0.0, 1.0, 0.0),
+            vector(-1.0, 0.0, 0.0),
+            vector(1.0, 0.0, 0.0),
+        )
+    }
+
+    #[test]
+    fn constructing_a_triangle() {
+        let t = Triangle::new(
+            point(0.0, 1.0, 0.0),
+            point(-1.0, 0.0, 0.0),
+            point(1.0, 0.0, 0.0),
+        );
+        assert_eq!(t.e1, vector(-1.0, -1.0, 0.0));
+        assert_eq!(t.e2, vector(1.0, -1.0, 0.0));
+        assert_eq!(t.normal, vector(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn finding_the_normal_on_a_triangle() {
+        let t = default_triangle();
+        let n1 = t.normal_at(&point(0.0, 0.5, 0.0));
+        let n2 = t.normal_at(&point(-0.5, 0.75, 0.0));
+        let n3 = t.normal_at(&point(0.5, 0.25, 0.0));
+        assert_eq!(n1, vector(0.0, 0.0, -1.0));
+        assert_eq!(n2, vector(0.0, 0.0, -1.0));
+        assert_eq!(n3, vector(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn triangle_has_a_bounding_box() {
+        let t = triangle(
+            point(-3.0, 7.0, 2.0),
+            point(6.0, 2.0, -4.0),
+            point(2.0, -1.0, -1.0),
+        );
+        let b = t.bounds();
+        assert_eq!(b.min, point(-3.0, -1.0, -4.0));
+        assert_eq!(b.max, point(6.0, 7.0, 2.0));
+    }
+
+    #[test]
+    fn intersecting_ray_parallel_to_triangle() {
+        let t = default_triangle();
+        let r = ray(point(0.0, -1.0, -2.0), vector(0.0, 1.0, 0.0));
+        assert!(t.intersect(&r).is_empty());
+    }
+
+    #[test]
+    fn ray_misses_p1_p3_edge() {
+        let t = default_triangle();
+        let r = ray(point(1.0, 1.0, -2.0), vector(0.0, 0.0, 1.0));
+        assert!(t.intersect(&r).is_empty());
+    }
+
+    #[test]
+    fn ray_misses_p1_p2_edge() {
+        let t = default_triangle();
+        let r = ray(point(-1.0, 1.0, -2.0), vector(0.0, 0.0, 1.0));
+        assert!(t.intersect(&r).is_empty());
+    }
+
+    #[test]
+    fn ray_misses_p2_p3_edge() {
+        let t = default_triangle();
+        let r = ray(point(0.0, -1.0, -2.0), vector(0.0, 0.0, 1.0));
+        assert!(t.intersect(&r).is_empty());
+    }
+
+    #[test]
+    fn ray_strikes_a_triangle() {
+        let t = default_triangle();
+        let r = ray(point(0.0, 0.5, -2.0), vector(0.0, 0.0, 1.0));
+        let xs = t.intersect(&r);
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs[0].t, 2.0);
+    }
+
+    #[test]
+    fn intersection_with_smooth_triangle_stores_u_and_v() {
+        let t = default_smooth_triangle();
+        let r = ray(point(-0.2, 0.3, -2.0), vector(0.0, 0.0, 1.0));
+        let xs = t.intersect(&r);
+        assert!((xs[0].u - 0.45).abs() < EPSILON);
+        assert!((xs[0].v - 0.25).abs() < EPSILON);
+    }
+
+    #[test]
+    fn smooth_triangle_uses_u_and_v_to_interpolate_the_normal() {
+        let t = default_smooth_triangle();
+        let i = intersection_with_uv(1.0, &t, 0.45, 0.25);
+        let n = t.normal_at_hit(&point(0.0, 0.0, 0.0), &i);
+        assert_eq!(n, vector(-0.5547, 0.83205, 0.0));
+    }
+
+    #[test]
+    fn preparing_the_normal_on_a_smooth_triangle() {
+        let t = default_smooth_triangle();
+        let i = intersection_with_uv(1.0, &t, 0.45, 0.25);
+        let r = ray(point(-0.2, 0.3, -2.0), vector(0.0, 0.0, 1.0));
+        let xs = vec![i.clone()];
+        let comps = prepare_computations(&i, &r, &xs);
+        assert_eq!(comps.normalv, vector(-0.5547, 0.83205, 0.0));
+    }
+}
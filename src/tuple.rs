@@ -11,11 +11,11 @@ pub struct Tuple {
 }
 
 impl Tuple {
-    fn is_point(&self) -> bool {
+    pub fn is_point(&self) -> bool {
         self.w == 1.0
     }
 
-    fn is_vector(&self) -> bool {
+    pub fn is_vector(&self) -> bool {
         self.w != 1.0
     }
 }
@@ -240,8 +240,8 @@ mod tests {
         assert_eq!(result.y, -4.2);
         assert_eq!(result.z, 3.1);
         assert_eq!(result.w, 1.0);
-        assert_eq!(result.is_point(), true);
-        assert_eq!(result.is_vector(), false);
+        assert!(result.is_point());
+        assert!(!result.is_vector());
     }
 
     #[test]
@@ -256,8 +256,8 @@ mod tests {
         assert_eq!(result.y, -4.2);
         assert_eq!(result.z, 3.1);
         assert_eq!(result.w, 0.0);
-        assert_eq!(result.is_point(), false);
-        assert_eq!(result.is_vector(), true);
+        assert!(!result.is_point());
+        assert!(result.is_vector());
     }
 
     #[test]
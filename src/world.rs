@@ -1,22 +1,28 @@
 use crate::{
+    bvh::{self, Bvh},
     color::Color,
-    intersections::{hit, prepare_computations, Computations, Intersection},
-    light::{lighting, PointLight},
+    intersections::{hit, prepare_computations, schlick, Computations, Intersection},
+    light::{ambient, direct_lighting, Light, LightSample, PointLight},
     materials::Material,
-    rays::{intersect, ray, Ray},
+    rays::{ray, Ray},
     shape::Shape,
     sphere::sphere,
     transformations::scaling,
-    tuple::{magnitude, normalise, point, Tuple},
+    tuple::{dot, point, Tuple},
 };
 
+// How many times a ray may bounce between reflective or refractive surfaces before giving up.
+pub const MAX_REFLECTION_DEPTH: u32 = 5;
+
 pub struct World {
-    pub light: PointLight,
-    pub objects: Vec<Shape>,
+    pub lights: Vec<Light>,
+    // Private so that any change to the objects throws away the hierarchy built over them.
+    objects: Vec<Shape>,
+    bvh: Option<Bvh>,
 }
 
-impl World {
-    pub fn default() -> World {
+impl Default for World {
+    fn default() -> Self {
         let light = PointLight::new(point(-10.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0));
         let m = Material::default()
             .color(Color::new(0.8, 1.0, 0.6))
@@ -26,68 +32,161 @@ impl World {
         s1.set_material(m);
         let mut s2 = sphere();
         s2.set_transform(scaling(0.5, 0.5, 0.5));
+        World::new(vec![Light::Point(light)], vec![s1, s2])
+    }
+}
+
+impl World {
+    pub fn new(lights: Vec<Light>, objects: Vec<Shape>) -> World {
         World {
-            light,
-            objects: vec![s1, s2],
+            lights,
+            objects,
+            bvh: None,
+        }
+    }
+
+    pub fn objects(&self) -> &[Shape] {
+        &self.objects
+    }
+
+    // Any change could move an object out of its box, so this drops the bounding volume
+    // hierarchy and build_bvh needs calling again.
+    pub fn objects_mut(&mut self) -> &mut Vec<Shape> {
+        self.bvh = None;
+        &mut self.objects
+    }
+
+    // Builds bounding volume hierarchies over the objects and inside any groups.
+    // Needs calling again after the objects are changed.
+    pub fn build_bvh(&mut self) {
+        for o in &mut self.objects {
+            o.build_bvh();
         }
+        self.bvh = Some(Bvh::build(&self.objects));
     }
 }
 
 pub fn intersect_world(w: &World, r: &Ray) -> Vec<Intersection> {
-    let mut result = vec![];
-    for o in &w.objects {
-        let temp = intersect(o, r);
-        result.extend_from_slice(&temp);
-    }
+    let mut result = match &w.bvh {
+        Some(bvh) => bvh::intersect(bvh, &w.objects, r),
+        None => w.objects.iter().flat_map(|o| o.intersect(r)).collect(),
+    };
     result.sort_by(|a, b| a.t.total_cmp(&b.t));
     result
 }
 
-pub fn shade_hit(w: &World, comps: &Computations) -> Color {
-    let shadowed = is_shadowed(w, &comps.over_point);
-    lighting(
-        comps.object.material(),
-        &comps.object,
-        &w.light,
-        &comps.over_point,
-        &comps.eyev,
-        &comps.normalv,
-        shadowed,
-    )
+pub fn shade_hit(w: &World, comps: &Computations, remaining: u32) -> Color {
+    let material = comps.object.material();
+    // Ambient light stands in for light bounced around the whole scene, so it is only
+    // counted once, taking its color from the first light.
+    let mut surface = match w.lights.first() {
+        Some(light) => ambient(material, &comps.object, light, &comps.over_point),
+        None => Color::black(),
+    };
+    // Each light contributes independently, and may be blocked when others aren't.
+    for light in &w.lights {
+        let intensity = intensity_at(w, light, &comps.over_point);
+        surface = surface
+            + direct_lighting(
+                material,
+                &comps.object,
+                light,
+                &comps.over_point,
+                &comps.eyev,
+                &comps.normalv,
+                intensity,
+            );
+    }
+    let reflected = reflected_color(w, comps, remaining);
+    let refracted = refracted_color(w, comps, remaining);
+
+    if material.reflective > 0.0 && material.transparency > 0.0 {
+        let reflectance = schlick(comps);
+        return surface + reflected * reflectance + refracted * (1.0 - reflectance);
+    }
+    surface + reflected + refracted
 }
 
-pub fn color_at(w: &World, r: &Ray) -> Color {
+pub fn color_at(w: &World, r: &Ray, remaining: u32) -> Color {
     let xs = intersect_world(w, r);
     let i = hit(&xs);
     match i {
         Some(i) => {
-            let comps = prepare_computations(i, r);
-            shade_hit(w, &comps)
+            let comps = prepare_computations(i, r, &xs);
+            shade_hit(w, &comps, remaining)
         }
         None => Color::new(0.0, 0.0, 0.0),
     }
 }
 
-pub fn is_shadowed(world: &World, point: &Tuple) -> bool {
-    let v = &world.light.position - point;
-    let distance = magnitude(&v);
-    let direction = normalise(&v);
-    let r = ray(point.clone(), direction);
+pub fn reflected_color(w: &World, comps: &Computations, remaining: u32) -> Color {
+    let reflective = comps.object.material().reflective;
+    // Stop once the ray has bounced too often, otherwise two parallel mirrors
+    // would recurse forever.
+    if reflective == 0.0 || remaining == 0 {
+        return Color::black();
+    }
+
+    let reflect_ray = ray(comps.over_point.clone(), comps.reflectv.clone());
+    let color = color_at(w, &reflect_ray, remaining - 1);
+    color * reflective
+}
+
+pub fn refracted_color(w: &World, comps: &Computations, remaining: u32) -> Color {
+    let transparency = comps.object.material().transparency;
+    if transparency == 0.0 || remaining == 0 {
+        return Color::black();
+    }
+
+    // Snell's law: sin(theta_i) * n1 = sin(theta_t) * n2
+    let n_ratio = comps.n1 / comps.n2;
+    let cos_i = dot(&comps.eyev, &comps.normalv);
+    let sin2_t = n_ratio * n_ratio * (1.0 - cos_i * cos_i);
+    if sin2_t > 1.0 {
+        // Total internal reflection.
+        return Color::black();
+    }
+
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let direction = &comps.normalv * (n_ratio * cos_i - cos_t) - &comps.eyev * n_ratio;
+    let refract_ray = ray(comps.under_point.clone(), direction);
+    color_at(w, &refract_ray, remaining - 1) * transparency
+}
+
+// How much of the light reaches the point, from 0.0 when every sample on the light is
+// blocked by something, up to 1.0 when none are.
+pub fn intensity_at(world: &World, light: &Light, point: &Tuple) -> f64 {
+    let samples = light.samples(point);
+    let visible = samples
+        .iter()
+        .filter(|sample| !is_shadowed(world, point, sample))
+        .count();
+    visible as f64 / samples.len() as f64
+}
+
+// Whether anything lies between the point and the sampled position on a light.
+// Directional lights are infinitely far away, so anything in the way blocks them.
+pub fn is_shadowed(world: &World, point: &Tuple, sample: &LightSample) -> bool {
+    let r = ray(point.clone(), sample.direction.clone());
     let intersections = intersect_world(world, &r);
     let h = hit(&intersections);
     let Some(h) = h else {
         return false;
     };
 
-    h.t < distance
+    h.t < sample.distance
 }
 
 #[cfg(test)]
 mod tests {
+    use std::f64::consts::PI;
 
     use crate::{
         color::Color,
         intersections::{intersection, prepare_computations},
+        light::{AreaLight, DirectionalLight, SpotLight},
+        patterns::test_pattern,
+        plane::plane,
         rays::ray,
         transformations::translation,
         tuple::vector,
@@ -108,7 +207,7 @@ mod tests {
         s2.set_transform(scaling(0.5, 0.5, 0.5));
 
         let w = World::default();
-        assert_eq!(w.light, light);
+        assert_eq!(w.lights, vec![Light::Point(light)]);
         assert_eq!(w.objects[0].material(), s1.material());
         assert_eq!(w.objects[0].transform(), s1.transform());
         assert_eq!(w.objects[1].material(), s2.material());
@@ -133,20 +232,27 @@ mod tests {
         let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
         let s = &w.objects[0];
         let i = intersection(4.0, s);
-        let comps = prepare_computations(&i, &r);
-        let c = shade_hit(&w, &comps);
+        let xs = vec![i.clone()];
+        let comps = prepare_computations(&i, &r, &xs);
+        let c = shade_hit(&w, &comps, MAX_REFLECTION_DEPTH);
         assert_eq!(c, Color::new(0.38066, 0.47583, 0.2855));
     }
 
     #[test]
     fn shading_an_intersection_from_the_inside() {
-        let mut w = World::default();
-        w.light = PointLight::new(point(0.0, 0.25, 0.0), Color::new(1.0, 1.0, 1.0));
+        let w = World {
+            lights: vec![Light::Point(PointLight::new(
+                point(0.0, 0.25, 0.0),
+                Color::new(1.0, 1.0, 1.0),
+            ))],
+            ..World::default()
+        };
         let r = ray(point(0.0, 0.0, 0.0), vector(0.0, 0.0, 1.0));
         let s = &w.objects[1];
         let i = intersection(0.5, s);
-        let comps = prepare_computations(&i, &r);
-        let c = shade_hit(&w, &comps);
+        let xs = vec![i.clone()];
+        let comps = prepare_computations(&i, &r, &xs);
+        let c = shade_hit(&w, &comps, MAX_REFLECTION_DEPTH);
         assert_eq!(c, Color::new(0.90498, 0.90498, 0.90498));
     }
 
@@ -154,7 +260,7 @@ mod tests {
     fn color_when_ray_misses() {
         let w = World::default();
         let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 1.0, 0.0));
-        let c = color_at(&w, &r);
+        let c = color_at(&w, &r, MAX_REFLECTION_DEPTH);
         assert_eq!(c, Color::new(0.0, 0.0, 0.0));
     }
 
@@ -162,7 +268,7 @@ mod tests {
     fn color_when_ray_hits() {
         let w = World::default();
         let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
-        let c = color_at(&w, &r);
+        let c = color_at(&w, &r, MAX_REFLECTION_DEPTH);
         assert_eq!(c, Color::new(0.38066, 0.47583, 0.2855));
     }
 
@@ -175,7 +281,7 @@ mod tests {
         let inner = &mut w.objects[1];
         inner.set_material(material);
         let r = ray(point(0.0, 0.0, 0.75), vector(0.0, 0.0, -1.0));
-        let c = color_at(&w, &r);
+        let c = color_at(&w, &r, MAX_REFLECTION_DEPTH);
         let inner = &w.objects[1];
         assert_eq!(c, inner.material().color);
     }
@@ -184,28 +290,226 @@ mod tests {
     fn no_shadow_when_nothing_between_point_and_light() {
         let w = World::default();
         let p = point(0.0, 10.0, 0.0);
-        assert_eq!(is_shadowed(&w, &p), false);
+        assert_eq!(intensity_at(&w, &w.lights[0], &p), 1.0);
     }
 
     #[test]
     fn shadow_when_object_between_point_and_light() {
         let w = World::default();
         let p = point(10.0, -10.0, 10.0);
-        assert_eq!(is_shadowed(&w, &p), true);
+        assert_eq!(intensity_at(&w, &w.lights[0], &p), 0.0);
     }
 
     #[test]
     fn no_shadow_when_object_behind_light() {
         let w = World::default();
         let p = point(-20.0, 20.0, -20.0);
-        assert_eq!(is_shadowed(&w, &p), false);
+        assert_eq!(intensity_at(&w, &w.lights[0], &p), 1.0);
     }
 
     #[test]
     fn no_shadow_when_object_behind_point() {
         let w = World::default();
         let p = point(-2.0, 2.0, -2.0);
-        assert_eq!(is_shadowed(&w, &p), false);
+        assert_eq!(intensity_at(&w, &w.lights[0], &p), 1.0);
+    }
+
+    #[test]
+    fn shadows_are_tested_for_each_light() {
+        let mut w = World::default();
+        let p = point(10.0, -10.0, 10.0);
+        let opposite = Light::Point(PointLight::new(
+            point(20.0, -20.0, 20.0),
+            Color::new(1.0, 1.0, 1.0),
+        ));
+        assert_eq!(intensity_at(&w, &w.lights[0], &p), 0.0);
+        assert_eq!(intensity_at(&w, &opposite, &p), 1.0);
+
+        // Shaded with both, the point is lit by the light that isn't blocked.
+        w.lights.push(opposite);
+        let s = &w.objects[0];
+        let normal = s.normal_at(&p);
+        let eye = -normal.clone();
+        let lit: Vec<Color> = w
+            .lights
+            .iter()
+            .map(|light| {
+                let intensity = intensity_at(&w, light, &p);
+                direct_lighting(s.material(), s, light, &p, &eye, &normal, intensity)
+            })
+            .collect();
+        assert_eq!(lit[0], Color::black());
+        assert_ne!(lit[1], Color::black());
+    }
+
+    #[test]
+    fn is_shadowed_tests_for_occlusion_between_two_points() {
+        let w = World::default();
+        let light_position = point(-10.0, 10.0, -10.0);
+        let examples = [
+            // Nothing between point and light.
+            (point(0.0, 10.0, 0.0), false),
+            // Object between point and light.
+            (point(10.0, -10.0, 10.0), true),
+            // Object behind the light.
+            (point(-20.0, 20.0, -20.0), false),
+            // Object behind the point.
+            (point(-2.0, 2.0, -2.0), false),
+        ];
+        for (p, expected) in examples {
+            let sample = LightSample::towards(&p, &light_position);
+            assert_eq!(is_shadowed(&w, &p, &sample), expected);
+        }
+    }
+
+    #[test]
+    fn point_lights_evaluate_the_light_intensity_at_a_point() {
+        let w = World::default();
+        let light = &w.lights[0];
+        let examples = [
+            (point(0.0, 1.0001, 0.0), 1.0),
+            (point(-1.0001, 0.0, 0.0), 1.0),
+            (point(0.0, 0.0, -1.0001), 1.0),
+            (point(0.0, 0.0, 1.0001), 0.0),
+            (point(1.0001, 0.0, 0.0), 0.0),
+            (point(0.0, -1.0001, 0.0), 0.0),
+            (point(0.0, 0.0, 0.0), 0.0),
+        ];
+        for (p, expected) in examples {
+            assert_eq!(intensity_at(&w, light, &p), expected);
+        }
+    }
+
+    #[test]
+    fn area_lights_evaluate_the_light_intensity_at_a_point() {
+        let w = World::default();
+        let light = Light::Area(AreaLight::new(
+            point(-0.5, -0.5, -5.0),
+            vector(1.0, 0.0, 0.0),
+            2,
+            vector(0.0, 1.0, 0.0),
+            2,
+            Color::white(),
+        ));
+        let examples = [
+            (point(0.0, 0.0, 2.0), 0.0),
+            (point(1.0, -1.0, 2.0), 0.25),
+            (point(1.5, 0.0, 2.0), 0.5),
+            (point(1.25, 1.25, 3.0), 0.75),
+            (point(0.0, 0.0, -2.0), 1.0),
+        ];
+        for (p, expected) in examples {
+            assert_eq!(intensity_at(&w, &light, &p), expected);
+        }
+    }
+
+    #[test]
+    fn spot_light_casts_shadows_from_its_position() {
+        let w = World::default();
+        let light = Light::Spot(SpotLight::new(
+            point(0.0, 0.0, -10.0),
+            vector(0.0, 0.0, 1.0),
+            PI / 8.0,
+            PI / 4.0,
+            Color::white(),
+        ));
+        assert_eq!(intensity_at(&w, &light, &point(0.0, 0.0, -1.0001)), 1.0);
+        assert_eq!(intensity_at(&w, &light, &point(0.0, 0.0, 1.0001)), 0.0);
+    }
+
+    #[test]
+    fn directional_light_shadow_rays_are_unbounded() {
+        let mut w = World::default();
+        let light = Light::Directional(DirectionalLight::new(
+            vector(0.0, -1.0, 0.0),
+            Color::white(),
+        ));
+        let p = point(0.0, -1.0001, 0.0);
+        assert_eq!(intensity_at(&w, &light, &p), 0.0);
+
+        // A blocker far further away than a faked sun would ever be.
+        w.objects.clear();
+        let mut s = sphere();
+        s.set_transform(translation(0.0, 1e9, 0.0) * scaling(10.0, 10.0, 10.0));
+        w.objects.push(s);
+        assert_eq!(intensity_at(&w, &light, &p), 0.0);
+        assert_eq!(intensity_at(&w, &light, &point(100.0, 0.0, 0.0)), 1.0);
+    }
+
+    #[test]
+    fn shade_hit_sums_the_contribution_of_each_light() {
+        let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let shade = |w: &World| {
+            let i = intersection(4.0, &w.objects[0]);
+            let xs = vec![i.clone()];
+            let comps = prepare_computations(&i, &r, &xs);
+            shade_hit(w, &comps, MAX_REFLECTION_DEPTH)
+        };
+
+        let key = Light::Point(PointLight::new(
+            point(-10.0, 10.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        ));
+        let fill = Light::Point(PointLight::new(
+            point(10.0, 0.0, -10.0),
+            Color::new(0.5, 0.5, 0.5),
+        ));
+        let mut w = World::default();
+        let key_only = shade(&w);
+        w.lights = vec![fill];
+        let fill_only = shade(&w);
+        w.lights.push(key);
+        // Ambient light is only counted once, from the first light.
+        let key_ambient = Color::new(0.8, 1.0, 0.6) * 0.1;
+        assert_eq!(shade(&w), key_only - key_ambient + fill_only);
+
+        w.lights.clear();
+        assert_eq!(shade(&w), Color::black());
+    }
+
+    #[test]
+    fn ambient_light_is_not_multiplied_by_the_number_of_lights() {
+        let mut w = World::default();
+        let m = Material::default().ambient(1.0).diffuse(0.0).specular(0.0);
+        w.objects[0].set_material(m);
+        let light = w.lights[0].clone();
+        w.lights = vec![light.clone(), light.clone(), light];
+        let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let i = intersection(4.0, &w.objects[0]);
+        let xs = vec![i.clone()];
+        let comps = prepare_computations(&i, &r, &xs);
+        assert_eq!(shade_hit(&w, &comps, MAX_REFLECTION_DEPTH), Color::white());
+    }
+
+    #[test]
+    fn intersect_world_with_bvh() {
+        let mut w = World::default();
+        let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let brute_force = intersect_world(&w, &r);
+        w.build_bvh();
+        assert_eq!(intersect_world(&w, &r), brute_force);
+
+        // Objects added after building are still found.
+        let mut s = sphere();
+        s.set_transform(translation(0.0, 0.0, 10.0));
+        w.objects_mut().push(s);
+        assert_eq!(intersect_world(&w, &r).len(), 6);
+    }
+
+    #[test]
+    fn moving_an_object_after_building_the_bvh() {
+        let mut w = World::default();
+        w.build_bvh();
+        // Same number of objects, but the first is now somewhere its old box doesn't cover.
+        w.objects_mut()[0].set_transform(translation(10.0, 0.0, 0.0));
+        let r = ray(point(10.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let xs = intersect_world(&w, &r);
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].t, 4.0);
+
+        // Rebuilding finds it too.
+        w.build_bvh();
+        assert_eq!(intersect_world(&w, &r), xs);
     }
 
     #[test]
@@ -214,14 +518,212 @@ mod tests {
         let s1 = sphere();
         let mut s2 = sphere();
         s2.set_transform(translation(0.0, 0.0, 10.0));
-        let w = World {
-            light,
-            objects: vec![s1, s2],
-        };
+        let w = World::new(vec![Light::Point(light)], vec![s1, s2]);
         let r = ray(point(0.0, 0.0, 5.0), vector(0.0, 0.0, 1.0));
         let i = intersection(4.0, &w.objects[1]);
-        let comps = prepare_computations(&i, &r);
-        let c = shade_hit(&w, &comps);
+        let xs = vec![i.clone()];
+        let comps = prepare_computations(&i, &r, &xs);
+        let c = shade_hit(&w, &comps, MAX_REFLECTION_DEPTH);
         assert_eq!(c, Color::new(0.1, 0.1, 0.1));
     }
+
+    #[test]
+    fn reflected_color_for_nonreflective_material() {
+        let mut w = World::default();
+        let r = ray(point(0.0, 0.0, 0.0), vector(0.0, 0.0, 1.0));
+        let shape = &mut w.objects[1];
+        shape.set_material(Material::default().ambient(1.0));
+        let i = intersection(1.0, &w.objects[1]);
+        let xs = vec![i.clone()];
+        let comps = prepare_computations(&i, &r, &xs);
+        let color = reflected_color(&w, &comps, MAX_REFLECTION_DEPTH);
+        assert_eq!(color, Color::black());
+    }
+
+    #[test]
+    fn reflected_color_for_reflective_material() {
+        let mut w = World::default();
+        let mut shape = plane();
+        shape.set_material(Material::default().reflective(0.5));
+        shape.set_transform(translation(0.0, -1.0, 0.0));
+        w.objects.push(shape);
+        let r = ray(
+            point(0.0, 0.0, -3.0),
+            vector(0.0, -2.0_f64.sqrt() / 2.0, 2.0_f64.sqrt() / 2.0),
+        );
+        let i = intersection(2.0_f64.sqrt(), &w.objects[2]);
+        let xs = vec![i.clone()];
+        let comps = prepare_computations(&i, &r, &xs);
+        let color = reflected_color(&w, &comps, MAX_REFLECTION_DEPTH);
+        assert_eq!(color, Color::new(0.19032, 0.2379, 0.14274));
+    }
+
+    #[test]
+    fn shade_hit_with_reflective_material() {
+        let mut w = World::default();
+        let mut shape = plane();
+        shape.set_material(Material::default().reflective(0.5));
+        shape.set_transform(translation(0.0, -1.0, 0.0));
+        w.objects.push(shape);
+        let r = ray(
+            point(0.0, 0.0, -3.0),
+            vector(0.0, -2.0_f64.sqrt() / 2.0, 2.0_f64.sqrt() / 2.0),
+        );
+        let i = intersection(2.0_f64.sqrt(), &w.objects[2]);
+        let xs = vec![i.clone()];
+        let comps = prepare_computations(&i, &r, &xs);
+        let color = shade_hit(&w, &comps, MAX_REFLECTION_DEPTH);
+        assert_eq!(color, Color::new(0.87677, 0.92436, 0.82918));
+    }
+
+    #[test]
+    fn color_at_with_mutually_reflective_surfaces() {
+        let light = PointLight::new(point(0.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0));
+        let mut lower = plane();
+        lower.set_material(Material::default().reflective(1.0));
+        lower.set_transform(translation(0.0, -1.0, 0.0));
+        let mut upper = plane();
+        upper.set_material(Material::default().reflective(1.0));
+        upper.set_transform(translation(0.0, 1.0, 0.0));
+        let w = World::new(vec![Light::Point(light)], vec![lower, upper]);
+        let r = ray(point(0.0, 0.0, 0.0), vector(0.0, 1.0, 0.0));
+        // Only needs to terminate.
+        color_at(&w, &r, MAX_REFLECTION_DEPTH);
+    }
+
+    #[test]
+    fn reflected_color_at_maximum_recursive_depth() {
+        let mut w = World::default();
+        let mut shape = plane();
+        shape.set_material(Material::default().reflective(0.5));
+        shape.set_transform(translation(0.0, -1.0, 0.0));
+        w.objects.push(shape);
+        let r = ray(
+            point(0.0, 0.0, -3.0),
+            vector(0.0, -2.0_f64.sqrt() / 2.0, 2.0_f64.sqrt() / 2.0),
+        );
+        let i = intersection(2.0_f64.sqrt(), &w.objects[2]);
+        let xs = vec![i.clone()];
+        let comps = prepare_computations(&i, &r, &xs);
+        let color = reflected_color(&w, &comps, 0);
+        assert_eq!(color, Color::black());
+    }
+
+    #[test]
+    fn refracted_color_with_opaque_surface() {
+        let w = World::default();
+        let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let xs = vec![
+            intersection(4.0, &w.objects[0]),
+            intersection(6.0, &w.objects[0]),
+        ];
+        let comps = prepare_computations(&xs[0], &r, &xs);
+        let c = refracted_color(&w, &comps, MAX_REFLECTION_DEPTH);
+        assert_eq!(c, Color::black());
+    }
+
+    #[test]
+    fn refracted_color_at_maximum_recursive_depth() {
+        let mut w = World::default();
+        let m = w.objects[0].material().clone();
+        w.objects[0].set_material(m.transparency(1.0).refractive_index(1.5));
+        let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let xs = vec![
+            intersection(4.0, &w.objects[0]),
+            intersection(6.0, &w.objects[0]),
+        ];
+        let comps = prepare_computations(&xs[0], &r, &xs);
+        let c = refracted_color(&w, &comps, 0);
+        assert_eq!(c, Color::black());
+    }
+
+    #[test]
+    fn refracted_color_under_total_internal_reflection() {
+        let mut w = World::default();
+        let m = w.objects[0].material().clone();
+        w.objects[0].set_material(m.transparency(1.0).refractive_index(1.5));
+        let r = ray(point(0.0, 0.0, 2.0_f64.sqrt() / 2.0), vector(0.0, 1.0, 0.0));
+        let xs = vec![
+            intersection(-(2.0_f64.sqrt()) / 2.0, &w.objects[0]),
+            intersection(2.0_f64.sqrt() / 2.0, &w.objects[0]),
+        ];
+        // Inside the sphere, so look at the second intersection.
+        let comps = prepare_computations(&xs[1], &r, &xs);
+        let c = refracted_color(&w, &comps, MAX_REFLECTION_DEPTH);
+        assert_eq!(c, Color::black());
+    }
+
+    #[test]
+    fn refracted_color_with_a_refracted_ray() {
+        let mut w = World::default();
+        let m = w.objects[0].material().clone();
+        w.objects[0].set_material(m.ambient(1.0).pattern(test_pattern()));
+        let m = w.objects[1].material().clone();
+        w.objects[1].set_material(m.transparency(1.0).refractive_index(1.5));
+        let r = ray(point(0.0, 0.0, 0.1), vector(0.0, 1.0, 0.0));
+        let xs = vec![
+            intersection(-0.9899, &w.objects[0]),
+            intersection(-0.4899, &w.objects[1]),
+            intersection(0.4899, &w.objects[1]),
+            intersection(0.9899, &w.objects[0]),
+        ];
+        let comps = prepare_computations(&xs[2], &r, &xs);
+        let c = refracted_color(&w, &comps, 5);
+        assert_eq!(c, Color::new(0.0, 0.99888, 0.04725));
+    }
+
+    #[test]
+    fn shade_hit_with_transparent_material() {
+        let mut w = World::default();
+        let mut floor = plane();
+        floor.set_transform(translation(0.0, -1.0, 0.0));
+        floor.set_material(Material::default().transparency(0.5).refractive_index(1.5));
+        w.objects.push(floor);
+        let mut ball = sphere();
+        ball.set_material(
+            Material::default()
+                .color(Color::new(1.0, 0.0, 0.0))
+                .ambient(0.5),
+        );
+        ball.set_transform(translation(0.0, -3.5, -0.5));
+        w.objects.push(ball);
+        let r = ray(
+            point(0.0, 0.0, -3.0),
+            vector(0.0, -2.0_f64.sqrt() / 2.0, 2.0_f64.sqrt() / 2.0),
+        );
+        let xs = vec![intersection(2.0_f64.sqrt(), &w.objects[2])];
+        let comps = prepare_computations(&xs[0], &r, &xs);
+        let color = shade_hit(&w, &comps, MAX_REFLECTION_DEPTH);
+        assert_eq!(color, Color::new(0.93642, 0.68642, 0.68642));
+    }
+
+    #[test]
+    fn shade_hit_with_reflective_transparent_material() {
+        let mut w = World::default();
+        let mut floor = plane();
+        floor.set_transform(translation(0.0, -1.0, 0.0));
+        floor.set_material(
+            Material::default()
+                .reflective(0.5)
+                .transparency(0.5)
+                .refractive_index(1.5),
+        );
+        w.objects.push(floor);
+        let mut ball = sphere();
+        ball.set_material(
+            Material::default()
+                .color(Color::new(1.0, 0.0, 0.0))
+                .ambient(0.5),
+        );
+        ball.set_transform(translation(0.0, -3.5, -0.5));
+        w.objects.push(ball);
+        let r = ray(
+            point(0.0, 0.0, -3.0),
+            vector(0.0, -2.0_f64.sqrt() / 2.0, 2.0_f64.sqrt() / 2.0),
+        );
+        let xs = vec![intersection(2.0_f64.sqrt(), &w.objects[2])];
+        let comps = prepare_computations(&xs[0], &r, &xs);
+        let color = shade_hit(&w, &comps, MAX_REFLECTION_DEPTH);
+        assert_eq!(color, Color::new(0.93391, 0.69643, 0.69243));
+    }
 }